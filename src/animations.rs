@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use std::time::Duration;
 
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
@@ -11,7 +12,12 @@ pub struct Animation {
 
 impl Animation {
     pub fn update(&mut self, time: &Time, sprite: &mut TextureAtlasSprite) {
-        self.timer.tick(time.delta());
+        self.update_with_delta(time.delta(), sprite);
+    }
+
+    // play the animation faster or slower than its timer by scaling the delta
+    pub fn update_with_delta(&mut self, delta: Duration, sprite: &mut TextureAtlasSprite) {
+        self.timer.tick(delta);
 
         if self.timer.just_finished() {
             self.current_frame = (self.current_frame + 1) % self.frames.len();
@@ -1,6 +1,8 @@
 use crate::{
+    game_state::AppState,
     map::{TILE_COUNT_X, TILE_COUNT_Y, TILE_SIZE},
-    player::{player_movement, Player},
+    player::Player,
+    tick::interpolate_translation,
     SCALE,
 };
 use bevy::prelude::*;
@@ -9,18 +11,27 @@ pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(camera_setup)
-            .add_system(camera_follow_player.after(player_movement));
+        app.add_startup_system(camera_setup).add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(camera_follow_player.after(interpolate_translation)),
+        );
     }
 }
 
+// The camera following the player (the UI camera also has a 'Camera' component)
+#[derive(Component)]
+pub struct MainCamera;
+
 fn camera_setup(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+    commands.spawn_bundle(UiCameraBundle::default());
 }
 
 fn camera_follow_player(
-    mut camera_query: Query<&mut Transform, (With<Camera>, Without<Player>)>,
-    player_query: Query<&Transform, (With<Player>, Without<Camera>)>,
+    mut camera_query: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
+    player_query: Query<&Transform, (With<Player>, Without<MainCamera>)>,
 ) {
     let mut camera_transform = camera_query.single_mut();
     let player_transform = player_query.single();
@@ -0,0 +1,180 @@
+use crate::{
+    game_state::AppState,
+    season::Season,
+    tick::{FixedTick, FixedUpdateStage},
+};
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        // keep the day length if it was configured before adding the plugin
+        if !app.world.contains_resource::<GameClock>() {
+            app.insert_resource(GameClock::new(Duration::from_secs(DAY_LENGTH_SECS)));
+        }
+
+        app.add_event::<Dawn>()
+            .add_event::<Dusk>()
+            .add_system_to_stage(FixedUpdateStage, advance_clock)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_game_clock))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(apply_daylight));
+    }
+}
+
+const DAY_LENGTH_SECS: u64 = 300;
+
+// when the phases start, as a part of the day (the day starts at midnight)
+const DAWN_START: f32 = 0.2;
+const DAY_START: f32 = 0.3;
+const DUSK_START: f32 = 0.75;
+const NIGHT_START: f32 = 0.85;
+
+const NIGHT_TINT: Color = Color::rgb(0.3, 0.35, 0.55);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayPhase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+// The in-game time, it only moves forward on ticks so it is part of the simulation
+pub struct GameClock {
+    day_length: Duration,
+    time_of_day: Duration,
+    day: u32,
+}
+
+impl GameClock {
+    pub fn new(day_length: Duration) -> Self {
+        GameClock {
+            day_length,
+            time_of_day: day_length.mul_f32(DAY_START),
+            day: 1,
+        }
+    }
+
+    pub fn day_length(&self) -> Duration {
+        self.day_length
+    }
+
+    // the current day, starting at 1
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    pub fn season(&self) -> Season {
+        Season::from_day(self.day)
+    }
+
+    // the time since midnight
+    pub fn time_of_day(&self) -> Duration {
+        self.time_of_day
+    }
+
+    // the time since the midnight of the first day
+    pub fn elapsed(&self) -> Duration {
+        self.day_length * (self.day - 1) + self.time_of_day
+    }
+
+    // how far into the day it is, from 0.0 (midnight) to 1.0
+    pub fn day_fraction(&self) -> f32 {
+        self.time_of_day.as_secs_f32() / self.day_length.as_secs_f32()
+    }
+
+    pub fn phase(&self) -> DayPhase {
+        match self.day_fraction() {
+            t if t < DAWN_START => DayPhase::Night,
+            t if t < DAY_START => DayPhase::Dawn,
+            t if t < DUSK_START => DayPhase::Day,
+            t if t < NIGHT_START => DayPhase::Dusk,
+            _ => DayPhase::Night,
+        }
+    }
+
+    // the color the world is tinted with, white during the day
+    pub fn daylight(&self) -> Color {
+        let t = self.day_fraction();
+        let night = match self.phase() {
+            DayPhase::Day => 0.0,
+            DayPhase::Night => 1.0,
+            DayPhase::Dawn => 1.0 - (t - DAWN_START) / (DAY_START - DAWN_START),
+            DayPhase::Dusk => (t - DUSK_START) / (NIGHT_START - DUSK_START),
+        };
+        let day = Vec4::from(Color::WHITE);
+        Color::from(day.lerp(Vec4::from(NIGHT_TINT), night))
+    }
+}
+
+// Sent when the sun rises, and when it sets
+pub struct Dawn {
+    pub day: u32,
+}
+
+pub struct Dusk {
+    pub day: u32,
+}
+
+// Sprites darkened at night, their color is replaced by the daylight so they should be white
+#[derive(Component)]
+pub struct Shaded;
+
+// The color of a shaded sprite in full daylight, for the sprites which are not white
+#[derive(Component)]
+pub struct Tint(pub Color);
+
+pub fn reset_game_clock(mut clock: ResMut<GameClock>) {
+    *clock = GameClock::new(clock.day_length);
+}
+
+pub fn advance_clock(
+    tick: Res<FixedTick>,
+    mut clock: ResMut<GameClock>,
+    mut dawn_events: EventWriter<Dawn>,
+    mut dusk_events: EventWriter<Dusk>,
+) {
+    let previous_phase = clock.phase();
+
+    let day_length = clock.day_length;
+    clock.time_of_day += tick.delta();
+    if clock.time_of_day >= day_length {
+        clock.time_of_day -= day_length;
+        clock.day += 1;
+    }
+
+    let phase = clock.phase();
+    if phase != previous_phase {
+        match phase {
+            DayPhase::Dawn => dawn_events.send(Dawn { day: clock.day }),
+            DayPhase::Dusk => dusk_events.send(Dusk { day: clock.day }),
+            _ => {}
+        }
+    }
+}
+
+pub fn apply_daylight(
+    clock: Res<GameClock>,
+    clear_color: Option<ResMut<ClearColor>>,
+    mut base_clear_color: Local<Option<Color>>,
+    mut sprite_query: Query<&mut Sprite, With<Shaded>>,
+    mut atlas_sprite_query: Query<(&mut TextureAtlasSprite, Option<&Tint>), With<Shaded>>,
+) {
+    let daylight = clock.daylight();
+
+    if let Some(mut clear_color) = clear_color {
+        let base = *base_clear_color.get_or_insert(clear_color.0);
+        clear_color.0 = base * Vec4::from(daylight);
+    }
+    for mut sprite in sprite_query.iter_mut() {
+        sprite.color = daylight;
+    }
+    for (mut sprite, tint) in atlas_sprite_query.iter_mut() {
+        sprite.color = match tint {
+            Some(tint) => tint.0 * Vec4::from(daylight),
+            None => daylight,
+        };
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    map::{TILE_COUNT_Y, TILE_SIZE},
+    tick::interpolate_translation,
+    SCALE,
+};
+use bevy::prelude::*;
+
+pub struct DepthPlugin;
+
+impl Plugin for DepthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::Update,
+            sort_by_depth.after(interpolate_translation),
+        );
+    }
+}
+
+// The layers of the game, from the farthest to the closest to the camera:
+// the ground tiles
+pub const GROUND_Z: f32 = 1.0;
+// what is drawn on the ground, under everything else (footsteps...)
+pub const GROUND_EFFECT_Z: f32 = 2.0;
+// the world sprites are sorted between these, the lower on the screen the closer
+pub const WORLD_MIN_Z: f32 = 10.0;
+pub const WORLD_MAX_Z: f32 = 100.0;
+// what is shown above the world (health bars, key hints, popups)
+pub const OVERLAY_Z: f32 = 200.0;
+// the resource counters, above everything
+pub const HUD_Z: f32 = 300.0;
+
+// the world sprites can't be farther than this from the center of the map
+const WORLD_HALF_HEIGHT: f32 = (TILE_COUNT_Y as f32 + 1.0) * TILE_SIZE * SCALE;
+
+// A world sprite drawn in front of the sprites above it on the screen, and behind the ones
+// below it. The anchor is where the sprite touches the ground, in pixels of the sprite from
+// its center (the feet of a character, the root of a tree)
+#[derive(Component)]
+pub struct YSorted {
+    pub anchor: f32,
+}
+
+// the z of a world sprite touching the ground at this height
+pub fn depth_at(y: f32) -> f32 {
+    let closeness = ((WORLD_HALF_HEIGHT - y) / (2.0 * WORLD_HALF_HEIGHT)).clamp(0.0, 1.0);
+    WORLD_MIN_Z + closeness * (WORLD_MAX_Z - WORLD_MIN_Z)
+}
+
+// runs after the interpolation, so it sorts the sprites where they are drawn
+pub fn sort_by_depth(mut query: Query<(&YSorted, &mut Transform)>) {
+    for (y_sorted, mut transform) in query.iter_mut() {
+        let ground = transform.translation.y + y_sorted.anchor * transform.scale.y;
+        transform.translation.z = depth_at(ground);
+    }
+}
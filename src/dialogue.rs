@@ -0,0 +1,705 @@
+use crate::{
+    clock::{Shaded, Tint},
+    depth::YSorted,
+    game_state::{AppState, InGame},
+    interaction::{update_interactions, Interactable, Interacted, InteractionKind},
+    loading::GameAssets,
+    map::{tile_to_world, MapLayout, TILE_SIZE},
+    player::{Player, PlayerInput, PLAYER_HALF_SIZE_Y},
+    quests::{is_valid_name, StartQuest},
+    resource_counter::{CoinResource, Item, ResourceCounter},
+    texture_atlas::AtlasHandle,
+    tick::{FixedTick, FixedUpdateStage},
+    SCALE,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::{collections::BTreeSet, fmt};
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        // the dialogues are read from their files with the assets, see 'GameData'
+        app.init_resource::<Dialogues>()
+            .init_resource::<DialogueFlags>()
+            .init_resource::<OpenDialogue>()
+            .add_system_to_stage(FixedUpdateStage, talk.after(update_interactions))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_talkers)
+                    .with_system(reset_dialogues),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(update_dialogue_box),
+            );
+    }
+}
+
+// letters typed per second in the dialogue box
+const TYPING_SPEED: f32 = 40.0;
+// the player walking this far from the one talking ends the conversation, relative
+// to the radius to interact with it
+const TALK_DISTANCE: f32 = 1.5;
+const VILLAGER_TINT: Color = Color::rgb(0.6, 0.9, 0.6);
+// the node name of the choices ending the conversation
+const END: &str = "end";
+
+// The coins or an item of the player, counted by the conditions and the effects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Carried {
+    Coins,
+    Item(Item),
+}
+
+impl Carried {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "coins" => Some(Carried::Coins),
+            _ => Item::from_name(name).map(Carried::Item),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Carried::Coins => "coins",
+            Carried::Item(item) => item.name(),
+        }
+    }
+
+    // whether the resource counter of the item counts this
+    pub fn counts(self, item: Option<&Item>) -> bool {
+        match self {
+            Carried::Coins => item.is_none(),
+            Carried::Item(carried) => item == Some(&carried),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    AtLeast(Carried, u32),
+    Below(Carried, u32),
+    Flag(String),
+    NotFlag(String),
+}
+
+impl Condition {
+    fn parse(words: &[&str]) -> Option<Self> {
+        match words {
+            [carried, ">=", amount] => Some(Condition::AtLeast(
+                Carried::from_name(carried)?,
+                amount.parse().ok()?,
+            )),
+            [carried, "<", amount] => Some(Condition::Below(
+                Carried::from_name(carried)?,
+                amount.parse().ok()?,
+            )),
+            ["not", flag] if is_valid_name(flag) => Some(Condition::NotFlag(flag.to_string())),
+            [flag] if is_valid_name(flag) => Some(Condition::Flag(flag.to_string())),
+            _ => None,
+        }
+    }
+
+    fn holds(&self, flags: &DialogueFlags, amounts: &[(Carried, u32)]) -> bool {
+        let amount = |carried: &Carried| {
+            amounts
+                .iter()
+                .find(|(counted, _)| counted == carried)
+                .map_or(0, |(_, amount)| *amount)
+        };
+        match self {
+            Condition::AtLeast(carried, min) => amount(carried) >= *min,
+            Condition::Below(carried, max) => amount(carried) < *max,
+            Condition::Flag(flag) => flags.contains(flag),
+            Condition::NotFlag(flag) => !flags.contains(flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    Give(Carried, u32),
+    Take(Carried, u32),
+    Set(String),
+    Clear(String),
+    StartQuest(String),
+}
+
+impl Effect {
+    fn parse(words: &[&str]) -> Option<Self> {
+        match words {
+            ["give", carried, amount] => Some(Effect::Give(
+                Carried::from_name(carried)?,
+                amount.parse().ok()?,
+            )),
+            ["take", carried, amount] => Some(Effect::Take(
+                Carried::from_name(carried)?,
+                amount.parse().ok()?,
+            )),
+            ["set", flag] if is_valid_name(flag) => Some(Effect::Set(flag.to_string())),
+            ["clear", flag] if is_valid_name(flag) => Some(Effect::Clear(flag.to_string())),
+            ["start", quest] if is_valid_name(quest) => Some(Effect::StartQuest(quest.to_string())),
+            _ => None,
+        }
+    }
+}
+
+// An answer of the player, it leads to another node of the conversation
+#[derive(Debug, Clone)]
+pub struct DialogueChoice {
+    pub target: String,
+    pub text: String,
+    pub conditions: Vec<Condition>,
+}
+
+// What is said at a step of the conversation, and the answers of the player
+#[derive(Debug, Clone)]
+pub struct DialogueNode {
+    pub name: String,
+    pub speaker: Option<String>,
+    pub text: String,
+    pub conditions: Vec<Condition>,
+    pub effects: Vec<Effect>,
+    pub choices: Vec<DialogueChoice>,
+}
+
+// A conversation read from a dialogue file, see 'assets/dialogues/forester.txt' for its format
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub speaker: String,
+    pub nodes: Vec<DialogueNode>,
+}
+
+#[derive(Debug)]
+pub enum DialogueError {
+    Line(String),
+    UnknownNode(String),
+    Empty,
+}
+
+impl fmt::Display for DialogueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DialogueError::Line(line) => write!(f, "invalid dialogue line: '{}'", line),
+            DialogueError::UnknownNode(name) => write!(f, "unknown dialogue node: '{}'", name),
+            DialogueError::Empty => write!(f, "the dialogue has no node"),
+        }
+    }
+}
+
+impl Conversation {
+    pub fn parse(text: &str) -> Result<Self, DialogueError> {
+        let mut conversation = Conversation {
+            speaker: String::new(),
+            nodes: Vec::new(),
+        };
+
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let parse_error = || DialogueError::Line(line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+            // the text after the first words of the line
+            let rest = |skipped: usize| words[skipped..].join(" ");
+
+            // the lines after a node describe it
+            let node = conversation.nodes.last_mut();
+            match (&words[..], node) {
+                (["speaker", _, ..], None) => conversation.speaker = rest(1),
+                (["speaker", _, ..], Some(node)) => node.speaker = Some(rest(1)),
+                (["node", name], _) => conversation.nodes.push(DialogueNode {
+                    name: name.to_string(),
+                    speaker: None,
+                    text: String::new(),
+                    conditions: Vec::new(),
+                    effects: Vec::new(),
+                    choices: Vec::new(),
+                }),
+                (["say", ..], Some(node)) => {
+                    if !node.text.is_empty() {
+                        node.text.push('\n');
+                    }
+                    node.text.push_str(&rest(1));
+                }
+                (["do", effect @ ..], Some(node)) => node
+                    .effects
+                    .push(Effect::parse(effect).ok_or_else(parse_error)?),
+                (["choice", target, _, ..], Some(node)) => node.choices.push(DialogueChoice {
+                    target: target.to_string(),
+                    text: rest(2),
+                    conditions: Vec::new(),
+                }),
+                (["if", condition @ ..], Some(node)) => {
+                    let condition = Condition::parse(condition).ok_or_else(parse_error)?;
+                    match node.choices.last_mut() {
+                        Some(choice) => choice.conditions.push(condition),
+                        None => node.conditions.push(condition),
+                    }
+                }
+                _ => return Err(parse_error()),
+            }
+        }
+
+        if conversation.nodes.is_empty() {
+            return Err(DialogueError::Empty);
+        }
+        for choice in conversation
+            .nodes
+            .iter()
+            .flat_map(|node| node.choices.iter())
+        {
+            let known = choice.target == END
+                || conversation
+                    .nodes
+                    .iter()
+                    .any(|node| node.name == choice.target);
+            if !known {
+                return Err(DialogueError::UnknownNode(choice.target.clone()));
+            }
+        }
+        Ok(conversation)
+    }
+
+    // the first node with the name whose conditions hold, any node when there is no name
+    fn find_node(
+        &self,
+        name: Option<&str>,
+        flags: &DialogueFlags,
+        amounts: &[(Carried, u32)],
+    ) -> Option<usize> {
+        self.nodes.iter().position(|node| {
+            name.map_or(true, |name| node.name == name)
+                && node
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.holds(flags, amounts))
+        })
+    }
+}
+
+// the dialogues of the game, each one is read from 'assets/dialogues/<name>.txt'
+pub const DIALOGUES: [&str; 2] = ["forester", "notice_board"];
+
+// Every conversation of the game, by name
+#[derive(Default)]
+pub struct Dialogues(HashMap<String, Conversation>);
+
+impl Dialogues {
+    pub fn get(&self, name: &str) -> Option<&Conversation> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: &str, conversation: Conversation) {
+        self.0.insert(name.to_string(), conversation);
+    }
+}
+
+// The flags set by the dialogues, they last for the whole game
+#[derive(Default)]
+pub struct DialogueFlags(BTreeSet<String>);
+
+impl DialogueFlags {
+    pub fn contains(&self, flag: &str) -> bool {
+        self.0.contains(flag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.0.iter()
+    }
+
+    pub fn set(&mut self, flag: &str) {
+        self.0.insert(flag.to_string());
+    }
+}
+
+// A villager or a sign the player talks to, with the name of its conversation
+#[derive(Component)]
+pub struct Talker {
+    pub conversation: String,
+}
+
+// The conversation going on, with how much of the text of its node is typed
+#[derive(Clone, PartialEq)]
+pub struct ActiveDialogue {
+    talker: Entity,
+    conversation: String,
+    node: usize,
+    typed: f32,
+    length: usize,
+}
+
+impl ActiveDialogue {
+    fn new(talker: Entity, conversation: &str, node: usize, text: &str) -> Self {
+        ActiveDialogue {
+            talker,
+            conversation: conversation.to_string(),
+            node,
+            typed: 0.0,
+            length: text.chars().count(),
+        }
+    }
+
+    pub fn talker(&self) -> Entity {
+        self.talker
+    }
+
+    pub fn conversation(&self) -> &str {
+        &self.conversation
+    }
+
+    // the index of the node in the conversation
+    pub fn node(&self) -> usize {
+        self.node
+    }
+
+    // the amount of letters of the text shown
+    pub fn typed(&self) -> usize {
+        self.typed as usize
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typed() < self.length
+    }
+
+    fn finish_typing(&mut self) {
+        self.typed = self.length as f32;
+    }
+}
+
+#[derive(Default)]
+pub struct OpenDialogue(pub Option<ActiveDialogue>);
+
+// The root node of the dialogue box
+#[derive(Component)]
+struct DialogueBox;
+
+// The text of the dialogue box typed one letter at a time
+#[derive(Component)]
+struct DialogueText;
+
+pub fn reset_dialogues(mut flags: ResMut<DialogueFlags>, mut open_dialogue: ResMut<OpenDialogue>) {
+    *flags = DialogueFlags::default();
+    open_dialogue.0 = None;
+}
+
+fn spawn_talkers(
+    game_assets: Res<GameAssets>,
+    map_layout: Res<MapLayout>,
+    texture_atlas_handle: Res<AtlasHandle>,
+    mut commands: Commands,
+) {
+    for (tile, conversation) in map_layout.signs.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: game_assets.sell_sign.clone(),
+                transform: Transform::from_scale(Vec3::splat(SCALE * 0.5))
+                    .with_translation(tile_to_world(*tile).extend(0.0)),
+                ..Default::default()
+            })
+            .insert(Talker {
+                conversation: conversation.clone(),
+            })
+            .insert(Shaded)
+            .insert(YSorted { anchor: -10.0 })
+            .insert(Interactable {
+                radius: TILE_SIZE * SCALE * 0.6,
+                prompt: game_assets.e_key.clone(),
+                kind: InteractionKind::Talk,
+            })
+            .insert(InGame);
+    }
+
+    for (tile, conversation) in map_layout.villagers.iter() {
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                // standing, facing down
+                sprite: TextureAtlasSprite::new(35),
+                texture_atlas: texture_atlas_handle.0.clone(),
+                transform: Transform::from_scale(Vec3::splat(SCALE))
+                    .with_translation(tile_to_world(*tile).extend(0.0)),
+                ..Default::default()
+            })
+            .insert(Talker {
+                conversation: conversation.clone(),
+            })
+            .insert(Shaded)
+            .insert(Tint(VILLAGER_TINT))
+            .insert(YSorted {
+                anchor: -PLAYER_HALF_SIZE_Y,
+            })
+            .insert(Interactable {
+                radius: TILE_SIZE * SCALE * 0.8,
+                prompt: game_assets.e_key.clone(),
+                kind: InteractionKind::Talk,
+            })
+            .insert(InGame);
+    }
+}
+
+fn carried_amounts<'a>(
+    counters: impl Iterator<Item = (&'a ResourceCounter, Option<&'a Item>)>,
+) -> Vec<(Carried, u32)> {
+    counters
+        .map(|(counter, item)| {
+            (
+                item.map_or(Carried::Coins, |item| Carried::Item(*item)),
+                counter.0,
+            )
+        })
+        .collect()
+}
+
+// go to the first node with the name whose conditions hold and apply its effects,
+// the conversation ends when there is none
+fn enter_node(
+    talker: Entity,
+    name: &str,
+    target: Option<&str>,
+    conversation: &Conversation,
+    flags: &mut DialogueFlags,
+    quest_events: &mut EventWriter<StartQuest>,
+    counter_query: &mut Query<
+        (&mut ResourceCounter, Option<&Item>),
+        Or<(With<CoinResource>, With<Item>)>,
+    >,
+) -> Option<ActiveDialogue> {
+    let amounts = carried_amounts(counter_query.iter());
+    let index = conversation.find_node(target, flags, &amounts)?;
+    let node = &conversation.nodes[index];
+
+    for effect in node.effects.iter() {
+        match effect {
+            Effect::Give(carried, amount) | Effect::Take(carried, amount) => {
+                for (mut counter, item) in counter_query.iter_mut() {
+                    if carried.counts(item) {
+                        counter.0 = match effect {
+                            Effect::Give(..) => counter.0 + amount,
+                            _ => counter.0.saturating_sub(*amount),
+                        };
+                    }
+                }
+            }
+            Effect::Set(flag) => {
+                flags.0.insert(flag.clone());
+            }
+            Effect::Clear(flag) => {
+                flags.0.remove(flag);
+            }
+            Effect::StartQuest(quest) => quest_events.send(StartQuest(quest.clone())),
+        }
+    }
+    Some(ActiveDialogue::new(talker, name, index, &node.text))
+}
+
+// - interacting with a talker starts its conversation, or skips the typing, or ends it
+// - the number keys pick an answer once the text is typed, or skip the typing
+// - the conversation ends when the player walks away
+fn talk(
+    tick: Res<FixedTick>,
+    input: Res<PlayerInput>,
+    dialogues: Res<Dialogues>,
+    mut flags: ResMut<DialogueFlags>,
+    mut open_dialogue: ResMut<OpenDialogue>,
+    mut interacted_events: EventReader<Interacted>,
+    mut quest_events: EventWriter<StartQuest>,
+    player_query: Query<&Transform, With<Player>>,
+    talker_query: Query<(&Talker, &Transform, &Interactable), Without<Player>>,
+    mut counter_query: Query<
+        (&mut ResourceCounter, Option<&Item>),
+        Or<(With<CoinResource>, With<Item>)>,
+    >,
+) {
+    let mut dialogue = open_dialogue.0.clone();
+
+    for event in interacted_events.iter() {
+        if event.kind != InteractionKind::Talk {
+            continue;
+        }
+        dialogue = match dialogue {
+            Some(mut current) if current.talker == event.entity => {
+                if current.is_typing() {
+                    current.finish_typing();
+                    Some(current)
+                } else {
+                    None
+                }
+            }
+            _ => talker_query
+                .get(event.entity)
+                .ok()
+                .and_then(|(talker, ..)| {
+                    let conversation = dialogues.get(&talker.conversation)?;
+                    enter_node(
+                        event.entity,
+                        &talker.conversation,
+                        None,
+                        conversation,
+                        &mut flags,
+                        &mut quest_events,
+                        &mut counter_query,
+                    )
+                }),
+        };
+    }
+
+    let player_pos = player_query
+        .get_single()
+        .map(|transform| transform.translation);
+    dialogue = dialogue.filter(
+        |dialogue| match (talker_query.get(dialogue.talker), player_pos) {
+            (Ok((_, transform, interactable)), Ok(player_pos)) => {
+                transform.translation.distance(player_pos) <= interactable.radius * TALK_DISTANCE
+            }
+            _ => false,
+        },
+    );
+
+    if let (Some(mut current), Some(choice)) = (dialogue.clone(), input.choice) {
+        let conversation = dialogues.get(&current.conversation).unwrap();
+        let node = &conversation.nodes[current.node];
+        let amounts = carried_amounts(counter_query.iter());
+        let picked = node.choices.get(choice as usize).filter(|choice| {
+            choice
+                .conditions
+                .iter()
+                .all(|condition| condition.holds(&flags, &amounts))
+        });
+
+        dialogue = match picked {
+            _ if current.is_typing() => {
+                current.finish_typing();
+                Some(current)
+            }
+            Some(picked) if picked.target == END => None,
+            Some(picked) => enter_node(
+                current.talker,
+                &current.conversation,
+                Some(&picked.target),
+                conversation,
+                &mut flags,
+                &mut quest_events,
+                &mut counter_query,
+            ),
+            None => Some(current),
+        };
+    }
+
+    if let Some(current) = dialogue.as_mut() {
+        current.typed =
+            (current.typed + TYPING_SPEED * tick.delta_seconds()).min(current.length as f32);
+    }
+    if open_dialogue.0 != dialogue {
+        open_dialogue.0 = dialogue;
+    }
+}
+
+// the dialogue box is built again for every node and once its text is typed,
+// in between only the typed text changes
+fn update_dialogue_box(
+    game_assets: Res<GameAssets>,
+    dialogues: Res<Dialogues>,
+    flags: Res<DialogueFlags>,
+    open_dialogue: Res<OpenDialogue>,
+    mut commands: Commands,
+    mut shown: Local<Option<(Entity, usize, bool)>>,
+    box_query: Query<Entity, With<DialogueBox>>,
+    mut text_query: Query<&mut Text, With<DialogueText>>,
+    counter_query: Query<(&ResourceCounter, Option<&Item>), Or<(With<CoinResource>, With<Item>)>>,
+) {
+    if !open_dialogue.is_changed() {
+        return;
+    }
+    let dialogue = open_dialogue.0.as_ref().and_then(|dialogue| {
+        let conversation = dialogues.get(&dialogue.conversation)?;
+        Some((dialogue, conversation, &conversation.nodes[dialogue.node]))
+    });
+    let typed_text = |node: &DialogueNode, dialogue: &ActiveDialogue| -> String {
+        node.text.chars().take(dialogue.typed()).collect()
+    };
+
+    let key = dialogue.map(|(dialogue, ..)| (dialogue.talker, dialogue.node, dialogue.is_typing()));
+    if *shown == key {
+        if let (Some((dialogue, _, node)), Ok(mut text)) = (dialogue, text_query.get_single_mut()) {
+            text.sections[0].value = typed_text(node, dialogue);
+        }
+        return;
+    }
+    *shown = key;
+
+    for dialogue_box in box_query.iter() {
+        commands.entity(dialogue_box).despawn_recursive();
+    }
+    let (dialogue, conversation, node) = match dialogue {
+        Some(dialogue) => dialogue,
+        None => return,
+    };
+
+    let speaker = node.speaker.as_ref().unwrap_or(&conversation.speaker);
+    let mut lines = Vec::new();
+    if !dialogue.is_typing() {
+        let amounts = carried_amounts(counter_query.iter());
+        for (i, choice) in node.choices.iter().enumerate() {
+            let available = choice
+                .conditions
+                .iter()
+                .all(|condition| condition.holds(&flags, &amounts));
+            let color = if available {
+                Color::rgb(1.0, 0.85, 0.3)
+            } else {
+                Color::GRAY
+            };
+            lines.push((format!("{}. {}", i + 1, choice.text), color));
+        }
+        let hint = match node.choices.len() {
+            0 => "Press E to leave".to_string(),
+            choices => format!("Press 1-{} to answer, E to leave", choices),
+        };
+        lines.push((hint, Color::GRAY));
+    }
+
+    let text_style = |color| TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 20.0,
+        color,
+    };
+    let text_bundle = |text: String, color| TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(4.0)),
+            max_size: Size::new(Val::Px(900.0), Val::Undefined),
+            ..Default::default()
+        },
+        text: Text::with_section(text, text_style(color), Default::default()),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(20.0),
+                    bottom: Val::Px(20.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(60.0), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(12.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(0.1, 0.07, 0.05, 0.85).into(),
+            ..Default::default()
+        })
+        .insert(DialogueBox)
+        .insert(InGame)
+        .with_children(|parent| {
+            parent.spawn_bundle(text_bundle(speaker.clone(), Color::rgb(0.6, 0.9, 0.6)));
+            parent
+                .spawn_bundle(text_bundle(typed_text(node, dialogue), Color::WHITE))
+                .insert(DialogueText);
+            for (line, color) in lines {
+                parent.spawn_bundle(text_bundle(line, color));
+            }
+        });
+}
@@ -0,0 +1,359 @@
+use crate::{
+    clock::{GameClock, Shaded},
+    depth::{sort_by_depth, YSorted},
+    game_state::{AppState, GameRng, InGame},
+    interaction::{update_interactions, Interactable, Interacted, InteractionKind},
+    loading::GameAssets,
+    map::{tile_to_world, world_to_tile, MapLayout, TILE_SIZE},
+    player::Player,
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{CharcoalResource, Item, ResourceCounter},
+    stamina::Stamina,
+    tick::{FixedTick, FixedUpdateStage},
+    trees::Tree,
+    weather::{change_weather, Weather, WeatherState},
+    SCALE,
+};
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+
+pub struct FirePlugin;
+
+impl Plugin for FirePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FireSimulation::default())
+            .add_event::<IgniteTree>()
+            .add_system_to_stage(FixedUpdateStage, strike_lightning.after(change_weather))
+            .add_system_to_stage(FixedUpdateStage, spread_fire.after(strike_lightning))
+            .add_system_to_stage(FixedUpdateStage, extinguish_fire.after(update_interactions))
+            .add_system_to_stage(
+                FixedUpdateStage,
+                collect_charcoal.after(update_interactions),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(reset_fire)
+                    .with_system(spawn_campfires),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(update_flames.after(sort_by_depth)),
+            );
+    }
+}
+
+// the fire spreads and burns on steps, not on every tick
+const FIRE_STEP_SECONDS: f32 = 1.0;
+// health lost by a burning tree on each step
+const FIRE_DAMAGE: i16 = 10;
+// chance for the fire to spread to a neighbor tree on each step, in the driest weather
+const SPREAD_CHANCE: f32 = 0.25;
+// chance for a campfire to ignite a tree next to it on each step, in the driest weather
+const CAMPFIRE_CHANCE: f32 = 0.02;
+// chance for the rain to put out a burning tree on each step
+const RAIN_EXTINGUISH_CHANCE: f32 = 0.3;
+// chance for a lightning to strike a tree during a storm, per second
+const LIGHTNING_CHANCE: f64 = 0.03;
+const EXTINGUISH_STAMINA_COST: f32 = 15.0;
+const CHARCOAL_PER_STUMP: u32 = 2;
+
+// The fire is a cellular simulation on the tile grid: on each step every burning tree
+// loses health and may ignite the trees on the 8 tiles around it
+pub struct FireSimulation {
+    step_timer: Timer,
+}
+
+impl Default for FireSimulation {
+    fn default() -> Self {
+        FireSimulation {
+            step_timer: Timer::from_seconds(FIRE_STEP_SECONDS, true),
+        }
+    }
+}
+
+// Set a tree on fire (lightning, campfires...)
+pub struct IgniteTree(pub Entity);
+
+#[derive(Component)]
+pub struct Burning;
+
+// What is left of a burned tree, the player collects charcoal from it
+#[derive(Component)]
+pub struct CharredStump;
+
+// Ignites the trees around it now and then, when the weather is dry
+#[derive(Component)]
+pub struct Campfire {
+    pub tile: IVec2,
+}
+
+// The flame shown on a burning tree
+#[derive(Component)]
+struct Flame(Entity);
+
+fn reset_fire(mut fire: ResMut<FireSimulation>) {
+    *fire = FireSimulation::default();
+}
+
+fn spawn_campfires(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    map_layout: Res<MapLayout>,
+) {
+    for tile in map_layout.campfires.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: game_assets.fire.clone(),
+                transform: Transform::from_scale(Vec3::splat(SCALE * 0.5))
+                    .with_translation(tile_to_world(*tile).extend(0.0)),
+                ..Default::default()
+            })
+            .insert(Campfire { tile: *tile })
+            .insert(YSorted { anchor: -13.0 })
+            .insert(InGame);
+    }
+}
+
+fn strike_lightning(
+    tick: Res<FixedTick>,
+    weather: Res<Weather>,
+    mut game_rng: ResMut<GameRng>,
+    mut ignite_events: EventWriter<IgniteTree>,
+    tree_query: Query<(Entity, &Transform), (With<Tree>, Without<Burning>)>,
+) {
+    if weather.state != WeatherState::Storm
+        || !game_rng.gen_bool(LIGHTNING_CHANCE * tick.delta_seconds() as f64)
+    {
+        return;
+    }
+
+    // the trees order in the query depends on the entities, not on the game
+    let mut trees: Vec<(IVec2, Entity)> = tree_query
+        .iter()
+        .map(|(entity, transform)| (world_to_tile(transform.translation.truncate()), entity))
+        .collect();
+    if trees.is_empty() {
+        return;
+    }
+    trees.sort_unstable_by_key(|(tile, entity)| (tile.x, tile.y, *entity));
+    let (_, tree) = trees[game_rng.gen_range(0..trees.len())];
+    ignite_events.send(IgniteTree(tree));
+}
+
+pub fn spread_fire(
+    tick: Res<FixedTick>,
+    clock: Res<GameClock>,
+    weather: Res<Weather>,
+    game_assets: Res<GameAssets>,
+    mut fire: ResMut<FireSimulation>,
+    mut game_rng: ResMut<GameRng>,
+    mut commands: Commands,
+    mut ignite_events: EventReader<IgniteTree>,
+    mut tree_query: Query<(Entity, &mut Tree, &Transform, Option<&Burning>)>,
+    campfire_query: Query<&Campfire>,
+) {
+    let mut ignited: Vec<Entity> = ignite_events.iter().map(|event| event.0).collect();
+
+    fire.step_timer.tick(tick.delta());
+    if fire.step_timer.just_finished() {
+        let dryness = clock.season().dryness() * weather.state.dryness();
+        let spread_chance = SPREAD_CHANCE * dryness * weather.state.wind_strength();
+
+        // the grid of trees, everything is sorted so the randomness is used in the same order
+        let mut grid: HashMap<IVec2, Vec<Entity>> = HashMap::default();
+        let mut burning: Vec<(IVec2, Entity)> = Vec::new();
+        for (entity, tree, transform, is_burning) in tree_query.iter() {
+            // felled on this tick, it is falling rather than burning
+            if tree.health <= 0 {
+                continue;
+            }
+            let tile = world_to_tile(transform.translation.truncate());
+            grid.entry(tile).or_default().push(entity);
+            if is_burning.is_some() {
+                burning.push((tile, entity));
+            }
+        }
+        for trees in grid.values_mut() {
+            trees.sort_unstable();
+        }
+        burning.sort_unstable_by_key(|(tile, entity)| (tile.x, tile.y, *entity));
+
+        for (tile, entity) in burning {
+            for neighbor in neighbor_tiles(tile) {
+                for tree in grid.get(&neighbor).into_iter().flatten() {
+                    if game_rng.gen::<f32>() < spread_chance {
+                        ignited.push(*tree);
+                    }
+                }
+            }
+
+            let (_, mut tree, transform, _) = tree_query.get_mut(entity).unwrap();
+            tree.health -= FIRE_DAMAGE;
+            if tree.health <= 0 {
+                commands.entity(entity).despawn();
+                spawn_charred_stump(&mut commands, &game_assets, transform.translation);
+            } else if weather.state == WeatherState::Rain
+                && game_rng.gen::<f32>() < RAIN_EXTINGUISH_CHANCE
+            {
+                commands
+                    .entity(entity)
+                    .remove::<Burning>()
+                    .remove::<Interactable>();
+            }
+        }
+
+        let mut campfires: Vec<IVec2> = campfire_query
+            .iter()
+            .map(|campfire| campfire.tile)
+            .collect();
+        campfires.sort_unstable_by_key(|tile| (tile.x, tile.y));
+        for tile in campfires {
+            for neighbor in neighbor_tiles(tile) {
+                for tree in grid.get(&neighbor).into_iter().flatten() {
+                    if game_rng.gen::<f32>() < CAMPFIRE_CHANCE * dryness {
+                        ignited.push(*tree);
+                    }
+                }
+            }
+        }
+    }
+
+    for entity in ignited {
+        if let Ok((_, tree, _, None)) = tree_query.get(entity) {
+            if tree.health > 0 {
+                commands
+                    .entity(entity)
+                    .insert(Burning)
+                    .insert(Interactable {
+                        radius: TILE_SIZE * SCALE * 0.7,
+                        prompt: game_assets.e_key.clone(),
+                        kind: InteractionKind::Extinguish,
+                    });
+            }
+        }
+    }
+}
+
+// the 8 tiles around a tile, always in the same order
+fn neighbor_tiles(tile: IVec2) -> impl Iterator<Item = IVec2> {
+    (-1..=1)
+        .flat_map(|y| (-1..=1).map(move |x| IVec2::new(x, y)))
+        .filter(|offset| *offset != IVec2::ZERO)
+        .map(move |offset| tile + offset)
+}
+
+fn spawn_charred_stump(commands: &mut Commands, game_assets: &GameAssets, position: Vec3) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: game_assets.charcoal.clone(),
+            transform: Transform::from_scale(Vec3::splat(SCALE))
+                .with_translation(position - Vec3::new(0.0, TILE_SIZE * SCALE * 0.3, 0.0)),
+            ..Default::default()
+        })
+        .insert(CharredStump)
+        .insert(Shaded)
+        .insert(YSorted { anchor: -2.0 })
+        .insert(Interactable {
+            radius: TILE_SIZE * SCALE * 0.6,
+            prompt: game_assets.e_key.clone(),
+            kind: InteractionKind::Harvest,
+        })
+        .insert(InGame);
+}
+
+// putting out a fire takes stamina, an exhausted player can't do it
+fn extinguish_fire(
+    mut interacted_events: EventReader<Interacted>,
+    mut commands: Commands,
+    mut player_query: Query<&mut Stamina, With<Player>>,
+    burning_query: Query<(), (With<Burning>, With<Tree>)>,
+) {
+    for event in interacted_events.iter() {
+        if event.kind != InteractionKind::Extinguish || burning_query.get(event.entity).is_err() {
+            continue;
+        }
+        let mut stamina = player_query.single_mut();
+        if stamina.is_exhausted() {
+            continue;
+        }
+
+        stamina.consume(EXTINGUISH_STAMINA_COST);
+        commands
+            .entity(event.entity)
+            .remove::<Burning>()
+            .remove::<Interactable>();
+    }
+}
+
+fn collect_charcoal(
+    game_assets: Res<GameAssets>,
+    mut interacted_events: EventReader<Interacted>,
+    mut progress_events: EventWriter<QuestProgress>,
+    mut commands: Commands,
+    stump_query: Query<&Transform, With<CharredStump>>,
+    mut charcoal_res_query: Query<&mut ResourceCounter, With<CharcoalResource>>,
+) {
+    for event in interacted_events.iter() {
+        if event.kind != InteractionKind::Harvest {
+            continue;
+        }
+        let stump_transform = match stump_query.get(event.entity) {
+            Ok(transform) => transform,
+            Err(_) => continue,
+        };
+
+        charcoal_res_query.single_mut().0 += CHARCOAL_PER_STUMP;
+        progress_events.send(QuestProgress::ItemCollected(
+            Item::Charcoal,
+            CHARCOAL_PER_STUMP,
+        ));
+        trigger_popup(
+            &mut commands,
+            &game_assets,
+            stump_transform.translation + Vec3::new(0.0, TILE_SIZE * SCALE * 0.5, 0.0),
+            Popup::text(format!("+{} charcoal", CHARCOAL_PER_STUMP))
+                .with_icon(game_assets.charcoal.clone()),
+        );
+        commands.entity(event.entity).despawn();
+    }
+}
+
+// keep a flickering flame on top of every burning tree, drawn right in front of it
+fn update_flames(
+    time: Res<Time>,
+    game_assets: Res<GameAssets>,
+    mut commands: Commands,
+    burning_query: Query<(Entity, &Transform), (With<Burning>, Without<Flame>)>,
+    mut flame_query: Query<(Entity, &Flame, &mut Transform), Without<Burning>>,
+) {
+    let flicker = 1.0 + 0.1 * (time.seconds_since_startup() as f32 * 12.0).sin();
+    let mut lit_trees = Vec::new();
+
+    for (flame_entity, flame, mut flame_transform) in flame_query.iter_mut() {
+        match burning_query.get(flame.0) {
+            Ok((_, tree_transform)) => {
+                flame_transform.translation = tree_transform.translation + Vec3::new(0.0, 0.0, 0.1);
+                flame_transform.scale = tree_transform.scale * 0.6 * flicker;
+                lit_trees.push(flame.0);
+            }
+            Err(_) => commands.entity(flame_entity).despawn(),
+        }
+    }
+
+    for (tree, tree_transform) in burning_query.iter() {
+        if !lit_trees.contains(&tree) {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    texture: game_assets.fire.clone(),
+                    transform: Transform::from_translation(
+                        tree_transform.translation + Vec3::new(0.0, 0.0, 0.1),
+                    )
+                    .with_scale(tree_transform.scale * 0.6),
+                    ..Default::default()
+                })
+                .insert(Flame(tree))
+                .insert(InGame);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    depth::GROUND_EFFECT_Z,
+    game_state::InGame,
+    map::Map,
+    movement::Velocity,
+    player::player_movement,
+    tick::{FixedTick, FixedUpdateStage},
+    SCALE,
+};
+use bevy::prelude::*;
+
+pub struct FootstepsPlugin;
+
+impl Plugin for FootstepsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(FixedUpdateStage, leave_footsteps.after(player_movement))
+            .add_system_to_stage(FixedUpdateStage, update_footstep_dust);
+    }
+}
+
+// time between two steps, at full speed or not
+const STEP_SECONDS: f32 = 0.25;
+const DUST_SECONDS: f32 = 0.4;
+const DUST_SIZE: f32 = 3.0 * SCALE;
+// the characters slower than this are not walking, only sliding to a stop
+const MIN_WALK_SPEED: f32 = 10.0;
+
+// A character kicking up dust, grass or mud from the ground when walking
+#[derive(Component)]
+pub struct Footsteps {
+    timer: Timer,
+    // from the center of the character to its feet
+    feet_offset: Vec2,
+    left_foot: bool,
+}
+
+impl Footsteps {
+    pub fn new(feet_offset: Vec2) -> Self {
+        Footsteps {
+            timer: Timer::from_seconds(STEP_SECONDS, true),
+            feet_offset,
+            left_foot: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct FootstepDust {
+    timer: Timer,
+    color: Color,
+}
+
+pub fn leave_footsteps(
+    tick: Res<FixedTick>,
+    map: Res<Map>,
+    mut commands: Commands,
+    mut query: Query<(&mut Footsteps, &Velocity, &Transform)>,
+) {
+    for (mut footsteps, velocity, transform) in query.iter_mut() {
+        if velocity.0.length() < MIN_WALK_SPEED {
+            footsteps.timer.reset();
+            continue;
+        }
+        footsteps.timer.tick(tick.delta());
+        if !footsteps.timer.just_finished() {
+            continue;
+        }
+
+        footsteps.left_foot = !footsteps.left_foot;
+        let feet = transform.translation.truncate() + footsteps.feet_offset;
+        let color = match map.terrain_at_world(feet).footstep_color() {
+            Some(color) => color,
+            None => continue,
+        };
+        let side = if footsteps.left_foot { -1.0 } else { 1.0 };
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(DUST_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(
+                    (feet + Vec2::new(side * 2.0 * SCALE, 0.0)).extend(GROUND_EFFECT_Z),
+                ),
+                ..Default::default()
+            })
+            .insert(FootstepDust {
+                timer: Timer::from_seconds(DUST_SECONDS, false),
+                color,
+            })
+            .insert(InGame);
+    }
+}
+
+// the dust rises a little, shrinks and fades
+fn update_footstep_dust(
+    tick: Res<FixedTick>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut FootstepDust, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut dust, mut transform, mut sprite) in query.iter_mut() {
+        dust.timer.tick(tick.delta());
+        if dust.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let progress = dust.timer.percent();
+        transform.translation.y += 4.0 * SCALE * tick.delta_seconds();
+        transform.scale = Vec3::splat(1.0 - 0.5 * progress);
+        sprite.color = *dust.color.clone().set_a(0.8 * (1.0 - progress));
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::ops::{Deref, DerefMut};
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(AppState::Loading)
+            .insert_resource(GameStats::default())
+            .insert_resource(GameRng::new(0))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(reset_game_stats)
+                    .with_system(reset_game_rng),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(pause_game))
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(resume_game))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(despawn_game));
+    }
+}
+
+// The states of the game, 'Paused' is pushed on top of 'Playing' so the game
+// can be resumed where it was left, same thing for the 'MainMenu' opened from the pause menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    Loading,
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+// Every entity spawned for a game session, despawned when the session ends
+#[derive(Component)]
+pub struct InGame;
+
+// Keep track of what happened during a game session, shown on the results screen
+#[derive(Default)]
+pub struct GameStats {
+    pub wood_chopped: u32,
+    pub trees_felled: u32,
+    pub coins_earned: u32,
+}
+
+// The random number generator of a game session, every randomness of the game comes from it
+// so a session can be played again from its seed
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+    // seed to use for the next game instead of a random one
+    pub next_seed: Option<u64>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            next_seed: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Deref for GameRng {
+    type Target = StdRng;
+
+    fn deref(&self) -> &StdRng {
+        &self.rng
+    }
+}
+
+impl DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+pub fn reset_game_rng(mut game_rng: ResMut<GameRng>) {
+    let seed = game_rng.next_seed.unwrap_or_else(rand::random);
+    game_rng.seed = seed;
+    game_rng.rng = StdRng::seed_from_u64(seed);
+}
+
+fn reset_game_stats(mut stats: ResMut<GameStats>) {
+    *stats = GameStats::default();
+}
+
+fn pause_game(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        keys.clear_just_pressed(KeyCode::Escape);
+        state.push(AppState::Paused).unwrap();
+    }
+}
+
+fn resume_game(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        keys.clear_just_pressed(KeyCode::Escape);
+        state.pop().unwrap();
+    }
+}
+
+fn despawn_game(mut commands: Commands, query: Query<Entity, With<InGame>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    game_state::AppState,
+    loading::{GameAssets, GameData, LoadingPlugin},
+    menu::MenuPlugin,
+    tick::FixedTick,
+    GamePlugins,
+};
+use bevy::{
+    asset::{AssetPlugin, FileAssetIo},
+    input::InputPlugin,
+    prelude::*,
+    transform::TransformPlugin,
+    window::WindowPlugin,
+};
+
+// Build the game without window nor rendering, the simulation runs one tick per 'app.update()'
+// - the assets are never loaded, the game uses placeholder handles instead, but the data files
+//   are read right away
+// - there is no menu, the game starts right away in the 'Playing' state
+pub fn headless_app() -> App {
+    let mut app = App::new();
+
+    let mut tick = FixedTick::from_hz(60.0);
+    tick.lockstep = true;
+
+    let data = GameData::read(FileAssetIo::get_root_path().join("assets"))
+        .unwrap_or_else(|errors| panic!("invalid data files:\n{}", errors.join("\n")));
+
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            exit_on_close: false,
+        })
+        .add_plugin(AssetPlugin)
+        .add_asset::<Image>()
+        .add_asset::<TextureAtlas>()
+        .insert_resource(GameAssets::default())
+        .insert_resource(tick)
+        .insert_resource(data.dialogues)
+        .insert_resource(data.merchant_catalog)
+        .insert_resource(data.quest_catalog)
+        .insert_resource(data.map_layout)
+        .add_plugins_with(GamePlugins, |group| {
+            group.disable::<LoadingPlugin>().disable::<MenuPlugin>()
+        })
+        .add_system_set(SystemSet::on_update(AppState::Loading).with_system(start_game));
+
+    app
+}
+
+fn start_game(mut state: ResMut<State<AppState>>) {
+    state.set(AppState::Playing).unwrap();
+}
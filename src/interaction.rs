@@ -0,0 +1,141 @@
+use crate::{
+    depth::OVERLAY_Z,
+    game_state::{AppState, InGame},
+    map::TILE_SIZE,
+    player::{chop_wood_action, Player, PlayerInput},
+    tick::{interpolate_translation, FixedUpdateStage},
+    SCALE,
+};
+use bevy::prelude::*;
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NearestInteractable>()
+            .add_event::<Interacted>()
+            .add_system_to_stage(
+                FixedUpdateStage,
+                update_interactions.after(chop_wood_action),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(update_key_hint.after(interpolate_translation)),
+            );
+    }
+}
+
+// What happens when the player interacts with an entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractionKind {
+    Sell,
+    Harvest,
+    Extinguish,
+    Hire,
+    Trade,
+    Talk,
+}
+
+// An entity the player can interact with when standing within its radius,
+// the prompt is the icon shown above the entity
+#[derive(Component)]
+pub struct Interactable {
+    pub radius: f32,
+    pub prompt: Handle<Image>,
+    pub kind: InteractionKind,
+}
+
+// Sent when the player interacts with the nearest interactable in range
+pub struct Interacted {
+    pub entity: Entity,
+    pub kind: InteractionKind,
+}
+
+// The interactable the player would interact with, updated every tick
+#[derive(Default)]
+pub struct NearestInteractable(pub Option<Entity>);
+
+// There is only one key hint, shown above the nearest interactable
+#[derive(Component)]
+pub struct KeyHint;
+
+const KEY_HINT_OFFSET: f32 = TILE_SIZE * SCALE * 0.5;
+
+pub fn update_interactions(
+    input: Res<PlayerInput>,
+    mut nearest: ResMut<NearestInteractable>,
+    mut interacted_events: EventWriter<Interacted>,
+    player_query: Query<&Transform, With<Player>>,
+    interactable_query: Query<(Entity, &Transform, &Interactable), Without<Player>>,
+) {
+    let player_pos = match player_query.get_single() {
+        Ok(transform) => transform.translation.truncate(),
+        Err(_) => return,
+    };
+
+    // ties are broken on the entity so the result does not depend on the query order
+    nearest.0 = interactable_query
+        .iter()
+        .map(|(entity, transform, interactable)| {
+            let distance = transform.translation.truncate().distance(player_pos);
+            (entity, distance, interactable)
+        })
+        .filter(|(_, distance, interactable)| *distance <= interactable.radius)
+        .min_by(|(a, a_distance, _), (b, b_distance, _)| {
+            a_distance.total_cmp(b_distance).then(a.cmp(b))
+        })
+        .map(|(entity, _, _)| entity);
+
+    if input.interact {
+        if let Some(entity) = nearest.0 {
+            let (_, _, interactable) = interactable_query.get(entity).unwrap();
+            interacted_events.send(Interacted {
+                entity,
+                kind: interactable.kind,
+            });
+        }
+    }
+}
+
+fn update_key_hint(
+    mut commands: Commands,
+    nearest: Res<NearestInteractable>,
+    interactable_query: Query<(&Transform, &Interactable), Without<KeyHint>>,
+    mut key_hint_query: Query<
+        (Entity, &mut Transform, &mut Handle<Image>),
+        (With<KeyHint>, Without<Interactable>),
+    >,
+) {
+    let target = nearest
+        .0
+        .and_then(|entity| interactable_query.get(entity).ok());
+
+    match (target, key_hint_query.get_single_mut()) {
+        (Some((transform, interactable)), Ok((_, mut hint_transform, mut hint_texture))) => {
+            hint_transform.translation = key_hint_position(transform);
+            *hint_texture = interactable.prompt.clone();
+        }
+        (Some((transform, interactable)), Err(_)) => {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1.0, 1.0, 1.0, 0.85),
+                        ..Default::default()
+                    },
+                    texture: interactable.prompt.clone(),
+                    transform: Transform::from_scale(Vec3::splat(SCALE * 0.5))
+                        .with_translation(key_hint_position(transform)),
+                    ..Default::default()
+                })
+                .insert(KeyHint)
+                .insert(InGame);
+        }
+        (None, Ok((key_hint, _, _))) => commands.entity(key_hint).despawn(),
+        (None, Err(_)) => {}
+    }
+}
+
+// above the interactable, in the overlay layer
+fn key_hint_position(transform: &Transform) -> Vec3 {
+    (transform.translation.truncate() + Vec2::new(0.0, KEY_HINT_OFFSET)).extend(OVERLAY_Z)
+}
@@ -0,0 +1,104 @@
+// bevy systems take their resources and queries as arguments
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+pub const RESOLUTION: f32 = 16.0 / 9.0;
+pub const SCALE: f32 = 5.0;
+
+pub mod animations;
+pub mod camera;
+pub mod clock;
+pub mod depth;
+pub mod dialogue;
+pub mod fire;
+pub mod footsteps;
+pub mod game_state;
+pub mod headless;
+pub mod interaction;
+pub mod loading;
+pub mod map;
+pub mod menu;
+pub mod merchant;
+pub mod movement;
+pub mod pathfinding;
+pub mod player;
+pub mod popup;
+pub mod quests;
+pub mod replay;
+pub mod resource_counter;
+pub mod save;
+pub mod season;
+pub mod stamina;
+pub mod texture_atlas;
+pub mod tick;
+pub mod trading_post;
+pub mod tree_feedback;
+pub mod trees;
+pub mod weather;
+pub mod wildlife;
+pub mod workers;
+
+use camera::CameraPlugin;
+use clock::ClockPlugin;
+use depth::DepthPlugin;
+use dialogue::DialoguePlugin;
+use fire::FirePlugin;
+use footsteps::FootstepsPlugin;
+use game_state::GameStatePlugin;
+use interaction::InteractionPlugin;
+use loading::LoadingPlugin;
+use map::MapPlugin;
+use menu::MenuPlugin;
+use merchant::MerchantPlugin;
+use player::PlayerPlugin;
+use popup::PopupPlugin;
+use quests::QuestPlugin;
+use replay::ReplayPlugin;
+use resource_counter::ResourceCounterPlugin;
+use save::SavePlugin;
+use stamina::StaminaPlugin;
+use texture_atlas::AtlasPlugin;
+use tick::TickPlugin;
+use trading_post::TradingPostPlugin;
+use tree_feedback::TreeFeedbackPlugin;
+use trees::TreePlugin;
+use weather::WeatherPlugin;
+use wildlife::WildlifePlugin;
+use workers::WorkersPlugin;
+
+// Every plugin of the game, to add after bevy's 'DefaultPlugins'
+pub struct GamePlugins;
+
+impl PluginGroup for GamePlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(GameStatePlugin)
+            .add(TickPlugin)
+            .add(ClockPlugin)
+            .add(ReplayPlugin)
+            .add(LoadingPlugin)
+            .add(MenuPlugin)
+            .add(CameraPlugin)
+            .add(DepthPlugin)
+            .add(AtlasPlugin)
+            .add(MapPlugin)
+            .add(PlayerPlugin)
+            .add(FootstepsPlugin)
+            .add(TreePlugin)
+            .add(WeatherPlugin)
+            .add(FirePlugin)
+            .add(TreeFeedbackPlugin)
+            .add(StaminaPlugin)
+            .add(InteractionPlugin)
+            .add(PopupPlugin)
+            .add(ResourceCounterPlugin)
+            .add(TradingPostPlugin)
+            .add(MerchantPlugin)
+            .add(DialoguePlugin)
+            .add(QuestPlugin)
+            .add(SavePlugin)
+            .add(WorkersPlugin)
+            .add(WildlifePlugin);
+    }
+}
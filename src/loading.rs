@@ -0,0 +1,328 @@
+use crate::{
+    dialogue::{Conversation, Dialogues, DIALOGUES},
+    game_state::AppState,
+    map::MapLayout,
+    merchant::MerchantCatalog,
+    quests::QuestCatalog,
+};
+use bevy::{
+    asset::{AssetLoader, HandleId, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use std::{fmt, fs, path::Path};
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TextFile>()
+            .init_asset_loader::<TextFileLoader>()
+            .add_system_set(SystemSet::on_enter(AppState::Loading).with_system(load_assets))
+            .add_system_set(SystemSet::on_update(AppState::Loading).with_system(check_assets))
+            .add_system_set(
+                SystemSet::on_exit(AppState::Loading).with_system(despawn_loading_screen),
+            );
+    }
+}
+
+// Every asset the game needs, loaded once before reaching the main menu
+// (the default value holds placeholder handles, used by the headless app)
+#[derive(Default)]
+pub struct GameAssets {
+    pub font: Handle<Font>,
+    pub sprite_sheet: Handle<Image>,
+    pub ground: Handle<Image>,
+    pub path: Handle<Image>,
+    pub water: Handle<Image>,
+    pub mud: Handle<Image>,
+    pub stone: Handle<Image>,
+    pub sand: Handle<Image>,
+    pub coin: Handle<Image>,
+    pub wood_log: Handle<Image>,
+    pub sell_sign: Handle<Image>,
+    pub e_key: Handle<Image>,
+    pub charcoal: Handle<Image>,
+    pub fire: Handle<Image>,
+    pub lumber_camp: Handle<Image>,
+    pub sapling: Handle<Image>,
+    // the files of the 'GameData'
+    pub data_files: Vec<Handle<TextFile>>,
+}
+
+impl GameAssets {
+    fn load(asset_server: &AssetServer) -> Self {
+        GameAssets {
+            font: asset_server.load("fonts/Fixedsys Excelsior 3.01 Regular.ttf"),
+            sprite_sheet: asset_server.load("sprite_sheet.png"),
+            ground: asset_server.load("ground.png"),
+            path: asset_server.load("path.png"),
+            water: asset_server.load("water.png"),
+            mud: asset_server.load("mud.png"),
+            stone: asset_server.load("stone.png"),
+            sand: asset_server.load("sand.png"),
+            coin: asset_server.load("coin.png"),
+            wood_log: asset_server.load("wood_log.png"),
+            sell_sign: asset_server.load("sell_sign.png"),
+            e_key: asset_server.load("E_key.png"),
+            charcoal: asset_server.load("charcoal.png"),
+            fire: asset_server.load("fire.png"),
+            lumber_camp: asset_server.load("lumber_camp.png"),
+            sapling: asset_server.load("sapling.png"),
+            data_files: GameData::paths()
+                .iter()
+                .map(|path| asset_server.load(path.as_str()))
+                .collect(),
+        }
+    }
+
+    fn handle_ids(&self) -> Vec<HandleId> {
+        let mut ids = vec![
+            self.font.id,
+            self.sprite_sheet.id,
+            self.ground.id,
+            self.path.id,
+            self.water.id,
+            self.mud.id,
+            self.stone.id,
+            self.sand.id,
+            self.coin.id,
+            self.wood_log.id,
+            self.sell_sign.id,
+            self.e_key.id,
+            self.charcoal.id,
+            self.fire.id,
+            self.lumber_camp.id,
+            self.sapling.id,
+        ];
+        ids.extend(self.data_files.iter().map(|handle| handle.id));
+        ids
+    }
+}
+
+// A text file of the assets, its content is parsed once every asset is loaded
+#[derive(TypeUuid)]
+#[uuid = "ca70c071-3493-4f0e-8dbb-72322fef86da"]
+pub struct TextFile(pub String);
+
+#[derive(Default)]
+struct TextFileLoader;
+
+impl AssetLoader for TextFileLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let text = String::from_utf8(bytes.to_vec())?;
+            load_context.set_default_asset(LoadedAsset::new(TextFile(text)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
+
+const MERCHANT_CATALOG_PATH: &str = "merchant.txt";
+const QUEST_CATALOG_PATH: &str = "quests.txt";
+const MAP_LAYOUT_PATH: &str = "map.txt";
+
+fn dialogue_path(name: &str) -> String {
+    format!("dialogues/{}.txt", name)
+}
+
+// The data of the game written in the text files of the assets, the files can be edited
+// without building the game again
+pub struct GameData {
+    pub dialogues: Dialogues,
+    pub merchant_catalog: MerchantCatalog,
+    pub quest_catalog: QuestCatalog,
+    pub map_layout: MapLayout,
+}
+
+impl GameData {
+    // the paths of the files, in the assets folder
+    pub fn paths() -> Vec<String> {
+        let mut paths = vec![
+            MERCHANT_CATALOG_PATH.to_string(),
+            QUEST_CATALOG_PATH.to_string(),
+            MAP_LAYOUT_PATH.to_string(),
+        ];
+        paths.extend(DIALOGUES.iter().map(|name| dialogue_path(name)));
+        paths
+    }
+
+    // parse every file, 'read' gives the content of a file from its path,
+    // the errors are listed with the path of their file
+    pub fn parse(read: impl Fn(&str) -> Result<String, String>) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+
+        let merchant_catalog = parse_file(
+            MERCHANT_CATALOG_PATH,
+            &read,
+            MerchantCatalog::parse,
+            &mut errors,
+        );
+        let quest_catalog = parse_file(QUEST_CATALOG_PATH, &read, QuestCatalog::parse, &mut errors);
+        let map_layout = parse_file(MAP_LAYOUT_PATH, &read, MapLayout::parse, &mut errors);
+        let mut dialogues = Dialogues::default();
+        for name in DIALOGUES {
+            let path = dialogue_path(name);
+            if let Some(conversation) = parse_file(&path, &read, Conversation::parse, &mut errors) {
+                dialogues.insert(name, conversation);
+            }
+        }
+
+        match (merchant_catalog, quest_catalog, map_layout) {
+            (Some(merchant_catalog), Some(quest_catalog), Some(map_layout))
+                if errors.is_empty() =>
+            {
+                Ok(GameData {
+                    dialogues,
+                    merchant_catalog,
+                    quest_catalog,
+                    map_layout,
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+
+    // read the files right away, without the asset server
+    pub fn read(assets_folder: impl AsRef<Path>) -> Result<Self, Vec<String>> {
+        let folder = assets_folder.as_ref();
+        GameData::parse(|path| fs::read_to_string(folder.join(path)).map_err(|e| e.to_string()))
+    }
+}
+
+fn parse_file<T, E: fmt::Display>(
+    path: &str,
+    read: &impl Fn(&str) -> Result<String, String>,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+    errors: &mut Vec<String>,
+) -> Option<T> {
+    match read(path).and_then(|text| parse(&text).map_err(|e| e.to_string())) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            errors.push(format!("{}: {}", path, e));
+            None
+        }
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingText;
+
+// request all the assets and spawn the loading screen showing the progress
+fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let game_assets = GameAssets::load(&asset_server);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "Loading...",
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(LoadingText);
+        });
+
+    commands.insert_resource(game_assets);
+}
+
+// count the loaded assets to show the progress, then go to the main menu once everything is
+// loaded and the data files are parsed, if an asset failed to load or a data file is invalid
+// list it on the loading screen and never start the game
+fn check_assets(
+    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    text_files: Res<Assets<TextFile>>,
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut reported: Local<bool>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+) {
+    if *reported {
+        return;
+    }
+
+    let handle_ids = game_assets.handle_ids();
+    let mut loaded = 0;
+    let mut failed = Vec::new();
+
+    for &id in handle_ids.iter() {
+        match asset_server.get_load_state(id) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => failed.push(
+                asset_server
+                    .get_handle_path(id)
+                    .map(|path| path.path().display().to_string())
+                    .unwrap_or_else(|| format!("{:?}", id)),
+            ),
+            _ => {}
+        }
+    }
+
+    let mut text = text_query.single_mut();
+    let mut report = |title: &str, lines: Vec<String>| {
+        error!("{}: {}", title, lines.join(", "));
+        text.sections[0].value = format!("{}:\n{}", title, lines.join("\n"));
+        text.sections[0].style.color = Color::RED;
+        *reported = true;
+    };
+
+    if !failed.is_empty() {
+        report("Failed to load assets", failed);
+    } else if loaded == handle_ids.len() {
+        let data = GameData::parse(|path| {
+            text_files
+                .get(path)
+                .map(|file| file.0.clone())
+                .ok_or_else(|| "not loaded".to_string())
+        });
+        match data {
+            Ok(data) => {
+                commands.insert_resource(data.dialogues);
+                commands.insert_resource(data.merchant_catalog);
+                commands.insert_resource(data.quest_catalog);
+                commands.insert_resource(data.map_layout);
+                state.set(AppState::MainMenu).unwrap();
+            }
+            Err(errors) => report("Invalid data files", errors),
+        }
+    } else {
+        text.sections[0].value = format!("Loading... {}/{}", loaded, handle_ids.len());
+    }
+}
+
+fn despawn_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
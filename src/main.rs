@@ -1,46 +1,65 @@
 use bevy::{prelude::*, window::PresentMode};
+use bevy_game::{
+    replay::{verify_replay, InputRecorder, Replay},
+    save::SaveFile,
+    GamePlugins, RESOLUTION,
+};
+use std::{env, process};
 
-pub const RESOLUTION: f32 = 16.0 / 9.0;
-pub const SCALE: f32 = 5.0;
+// the contracts and the dialogue flags are kept in this file between games
+const SAVE_PATH: &str = "save.txt";
 
-mod animations;
-mod camera;
-mod map;
-mod player;
-mod resource_counter;
-mod sell_sign;
-mod sprite_popup;
-mod texture_atlas;
-mod trees;
+// usage:
+//   bevy_game                    play the game
+//   bevy_game --record <file>    play the game and record the game sessions to the file, the
+//                                save file is not used so the sessions start from a new game
+//   bevy_game --replay <file>    play the recorded session headless and check its state hash
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut recorder = None;
+    let mut save_file = Some(SaveFile::new(SAVE_PATH));
 
-use camera::CameraPlugin;
-use map::MapPlugin;
-use player::PlayerPlugin;
-use resource_counter::ResourceCounterPlugin;
-use sell_sign::SellSignPlugin;
-use sprite_popup::SpritePopupPlugin;
-use texture_atlas::AtlasPlugin;
-use trees::TreePlugin;
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {}
+        ["--record", path] => {
+            recorder = Some(InputRecorder::new(path));
+            save_file = None;
+        }
+        ["--replay", path] => {
+            match Replay::load(path).and_then(|replay| verify_replay(&replay)) {
+                Ok(()) => println!("{}: ok", path),
+                Err(e) => {
+                    eprintln!("{}: {}", path, e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {
+            eprintln!("usage: bevy_game [--record <file> | --replay <file>]");
+            process::exit(2);
+        }
+    }
 
-fn main() {
     let height = 900.0;
 
-    App::new()
-        .insert_resource(WindowDescriptor {
-            width: height * RESOLUTION,
-            height,
-            present_mode: PresentMode::Fifo,
-            ..Default::default()
-        })
-        .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
-        .add_plugins(DefaultPlugins)
-        .add_plugin(CameraPlugin)
-        .add_plugin(AtlasPlugin)
-        .add_plugin(MapPlugin)
-        .add_plugin(PlayerPlugin)
-        .add_plugin(TreePlugin)
-        .add_plugin(SpritePopupPlugin)
-        .add_plugin(ResourceCounterPlugin)
-        .add_plugin(SellSignPlugin)
-        .run();
+    let mut app = App::new();
+    app.insert_resource(WindowDescriptor {
+        width: height * RESOLUTION,
+        height,
+        present_mode: PresentMode::Fifo,
+        ..Default::default()
+    })
+    .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
+    .add_plugins(DefaultPlugins)
+    .add_plugins(GamePlugins);
+
+    if let Some(recorder) = recorder {
+        app.insert_resource(recorder);
+    }
+    if let Some(save_file) = save_file {
+        app.insert_resource(save_file);
+    }
+
+    app.run();
 }
@@ -1,5 +1,14 @@
-use crate::SCALE;
-use bevy::prelude::*;
+use crate::{
+    clock::Shaded,
+    depth::GROUND_Z,
+    game_state::{AppState, InGame},
+    loading::GameAssets,
+    resource_counter::Item,
+    trading_post::TradeOffer,
+    SCALE,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::fmt;
 
 pub const TILE_SIZE: f32 = 32.0;
 
@@ -9,34 +18,277 @@ pub const TILE_COUNT_Y: usize = 8;
 
 pub struct MapPlugin;
 
-#[derive(Deref, DerefMut)]
-pub struct Map(pub Vec<Entity>);
+// The game entities placed on the map, and the terrain of every tile
+#[derive(Default)]
+pub struct Map {
+    pub entities: Vec<Entity>,
+    terrain: HashMap<IVec2, Terrain>,
+}
+
+impl Map {
+    // the tiles outside of the map are water
+    pub fn terrain_at(&self, tile: IVec2) -> Terrain {
+        self.terrain.get(&tile).copied().unwrap_or(Terrain::Water)
+    }
+
+    pub fn terrain_at_world(&self, position: Vec2) -> Terrain {
+        self.terrain_at(world_to_tile(position))
+    }
+
+    pub fn is_walkable(&self, position: Vec2) -> bool {
+        self.terrain_at_world(position).is_walkable()
+    }
+
+    pub fn speed_multiplier(&self, position: Vec2) -> f32 {
+        self.terrain_at_world(position).speed_multiplier()
+    }
+
+    pub fn can_grow_trees(&self, tile: IVec2) -> bool {
+        self.terrain_at(tile).can_grow_trees()
+    }
+}
+
+// The ground of a tile, each tile of the map has it as a component
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Terrain {
+    Grass,
+    Path,
+    Water,
+    Mud,
+    Stone,
+    Sand,
+}
+
+impl Terrain {
+    pub fn is_walkable(self) -> bool {
+        self != Terrain::Water
+    }
+
+    pub fn speed_multiplier(self) -> f32 {
+        match self {
+            Terrain::Grass => 1.0,
+            Terrain::Path => 1.25,
+            Terrain::Water => 0.0,
+            Terrain::Mud => 0.6,
+            Terrain::Stone => 1.1,
+            Terrain::Sand => 0.8,
+        }
+    }
+
+    pub fn can_grow_trees(self) -> bool {
+        self == Terrain::Grass
+    }
+
+    // the color of what is kicked up by the characters walking on the tile, if anything
+    pub fn footstep_color(self) -> Option<Color> {
+        match self {
+            Terrain::Grass => Some(Color::rgb(0.36, 0.58, 0.22)),
+            Terrain::Path => Some(Color::rgb(0.87, 0.75, 0.55)),
+            Terrain::Mud => Some(Color::rgb(0.3, 0.21, 0.13)),
+            Terrain::Sand => Some(Color::rgb(0.93, 0.88, 0.72)),
+            Terrain::Water | Terrain::Stone => None,
+        }
+    }
+
+    fn texture(self, game_assets: &GameAssets) -> Handle<Image> {
+        match self {
+            Terrain::Grass => game_assets.ground.clone(),
+            Terrain::Path => game_assets.path.clone(),
+            Terrain::Water => game_assets.water.clone(),
+            Terrain::Mud => game_assets.mud.clone(),
+            Terrain::Stone => game_assets.stone.clone(),
+            Terrain::Sand => game_assets.sand.clone(),
+        }
+    }
+
+    // the symbol of the terrain in the map file
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(Terrain::Grass),
+            '=' => Some(Terrain::Path),
+            '~' => Some(Terrain::Water),
+            ',' => Some(Terrain::Mud),
+            '#' => Some(Terrain::Stone),
+            ':' => Some(Terrain::Sand),
+            _ => None,
+        }
+    }
+}
+
+// What is placed on the map besides the ground, read from 'assets/map.txt',
+// positions are in tiles from the map center
+pub struct MapLayout {
+    pub trading_posts: Vec<TradingPostData>,
+    pub campfires: Vec<IVec2>,
+    // where the workers are hired and bring their wood
+    pub lumber_camps: Vec<IVec2>,
+    // where the travelling merchant stands on the days of its visits
+    pub merchant: IVec2,
+    // the signs and the villagers the player talks to, with the name of their dialogue
+    pub signs: Vec<(IVec2, String)>,
+    pub villagers: Vec<(IVec2, String)>,
+    // the tiles missing from here are grass
+    pub terrain: HashMap<IVec2, Terrain>,
+}
+
+pub struct TradingPostData {
+    pub tile: IVec2,
+    pub offers: Vec<TradeOffer>,
+}
+
+// an empty map of grass, until the layout is read from 'assets/map.txt'
+impl Default for MapLayout {
+    fn default() -> Self {
+        MapLayout {
+            trading_posts: Vec::new(),
+            campfires: Vec::new(),
+            lumber_camps: Vec::new(),
+            merchant: IVec2::ZERO,
+            signs: Vec::new(),
+            villagers: Vec::new(),
+            terrain: HashMap::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Line(String),
+    Rows(usize),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Line(line) => write!(f, "invalid map line: '{}'", line),
+            MapError::Rows(rows) => write!(
+                f,
+                "the map has {} rows of tiles instead of {}",
+                rows,
+                2 * TILE_COUNT_Y + 1
+            ),
+        }
+    }
+}
+
+impl MapLayout {
+    pub fn parse(text: &str) -> Result<Self, MapError> {
+        let mut layout = MapLayout::default();
+        let mut rows = 0;
+
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let parse_error = || MapError::Line(line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let tile = |x: &str, y: &str| -> Result<IVec2, MapError> {
+                Ok(IVec2::new(
+                    x.parse().map_err(|_| parse_error())?,
+                    y.parse().map_err(|_| parse_error())?,
+                ))
+            };
+
+            // the offers follow their trading post
+            let post = layout.trading_posts.last_mut();
+            match (&words[..], post) {
+                (["post", x, y], _) => layout.trading_posts.push(TradingPostData {
+                    tile: tile(x, y)?,
+                    offers: Vec::new(),
+                }),
+                (["buys", item, multiplier, stock], Some(post)) => {
+                    post.offers.push(TradeOffer::new(
+                        Item::from_name(item).ok_or_else(parse_error)?,
+                        multiplier.parse().map_err(|_| parse_error())?,
+                        stock.parse().map_err(|_| parse_error())?,
+                    ))
+                }
+                (["campfire", x, y], _) => layout.campfires.push(tile(x, y)?),
+                (["camp", x, y], _) => layout.lumber_camps.push(tile(x, y)?),
+                (["merchant", x, y], _) => layout.merchant = tile(x, y)?,
+                (["sign", x, y, dialogue], _) => {
+                    layout.signs.push((tile(x, y)?, dialogue.to_string()))
+                }
+                (["villager", x, y, dialogue], _) => {
+                    layout.villagers.push((tile(x, y)?, dialogue.to_string()))
+                }
+                (["row", tiles], _) if tiles.chars().count() == 2 * TILE_COUNT_X + 1 => {
+                    let y = TILE_COUNT_Y as i32 - rows as i32;
+                    for (i, symbol) in tiles.chars().enumerate() {
+                        let x = i as i32 - TILE_COUNT_X as i32;
+                        let terrain = Terrain::from_symbol(symbol).ok_or_else(parse_error)?;
+                        if terrain != Terrain::Grass {
+                            layout.terrain.insert(IVec2::new(x, y), terrain);
+                        }
+                    }
+                    rows += 1;
+                }
+                _ => return Err(parse_error()),
+            }
+        }
+
+        if rows != 2 * TILE_COUNT_Y + 1 {
+            return Err(MapError::Rows(rows));
+        }
+        Ok(layout)
+    }
+}
+
+// the world position of the center of a tile
+pub fn tile_to_world(tile: IVec2) -> Vec2 {
+    tile.as_vec2() * TILE_SIZE * SCALE
+}
+
+// the tile a world position is in
+pub fn world_to_tile(position: Vec2) -> IVec2 {
+    (position / (TILE_SIZE * SCALE)).round().as_ivec2()
+}
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Map(Vec::new()))
-            .add_startup_system(spawn_map);
+        // the layout is read from its file with the assets, see 'GameData'
+        app.init_resource::<Map>()
+            .init_resource::<MapLayout>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_map));
     }
 }
 
-pub fn spawn_map(mut commands: Commands, mut map: ResMut<Map>, asset_server: Res<AssetServer>) {
-    let texture_handle = asset_server.load("ground.png");
+pub fn spawn_map(
+    mut commands: Commands,
+    mut map: ResMut<Map>,
+    game_assets: Res<GameAssets>,
+    map_layout: Res<MapLayout>,
+) {
+    // the previous game entities were despawned, only keep track of the new ones
+    map.entities.clear();
+    map.terrain.clear();
 
     for y in -(TILE_COUNT_Y as i32)..=TILE_COUNT_Y as i32 {
         for x in -(TILE_COUNT_X as i32)..=TILE_COUNT_X as i32 {
-            map.push(
+            let tile = IVec2::new(x, y);
+            let terrain = map_layout
+                .terrain
+                .get(&tile)
+                .copied()
+                .unwrap_or(Terrain::Grass);
+            map.terrain.insert(tile, terrain);
+
+            map.entities.push(
                 commands
                     .spawn_bundle(SpriteBundle {
-                        texture: texture_handle.clone(),
+                        texture: terrain.texture(&game_assets),
                         transform: Transform::from_scale(Vec3::splat(SCALE)).with_translation(
                             Vec3::new(
                                 TILE_SIZE * SCALE * x as f32,
                                 TILE_SIZE * SCALE * y as f32,
-                                1.0,
+                                GROUND_Z,
                             ),
                         ),
                         ..Default::default()
                     })
+                    .insert(terrain)
+                    .insert(Shaded)
                     .id(),
             );
         }
@@ -46,5 +298,6 @@ pub fn spawn_map(mut commands: Commands, mut map: ResMut<Map>, asset_server: Res
         .spawn()
         .insert(Transform::default())
         .insert(GlobalTransform::default())
-        .push_children(&map[..]);
+        .insert(InGame)
+        .push_children(&map.entities[..]);
 }
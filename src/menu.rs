@@ -0,0 +1,202 @@
+use crate::{
+    game_state::{AppState, GameStats},
+    loading::GameAssets,
+};
+use bevy::{app::AppExit, prelude::*};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(menu_button_action)
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(spawn_main_menu))
+            .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(spawn_pause_menu))
+            .add_system_set(SystemSet::on_exit(AppState::Paused).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(spawn_results))
+            .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(despawn_menu));
+    }
+}
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.3);
+
+// The root node of a menu screen, despawned (with all its children) when leaving the screen
+#[derive(Component)]
+struct Menu;
+
+// What a menu button does when clicked
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    NewGame,
+    Continue,
+    MainMenu,
+    EndGame,
+    Quit,
+}
+
+impl MenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuButton::NewGame => "New game",
+            MenuButton::Continue => "Continue",
+            MenuButton::MainMenu => "Main menu",
+            MenuButton::EndGame => "End game",
+            MenuButton::Quit => "Quit",
+        }
+    }
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    state: Res<State<AppState>>,
+) {
+    let mut buttons = vec![MenuButton::NewGame];
+    // a game is still running under the main menu if it was opened from the pause menu
+    if state.inactives().contains(&AppState::Playing) {
+        buttons.push(MenuButton::Continue);
+    }
+    buttons.push(MenuButton::Quit);
+
+    spawn_menu(&mut commands, &game_assets, &["bevy_game"], &buttons);
+}
+
+fn spawn_pause_menu(mut commands: Commands, game_assets: Res<GameAssets>) {
+    spawn_menu(
+        &mut commands,
+        &game_assets,
+        &["Paused"],
+        &[
+            MenuButton::Continue,
+            MenuButton::MainMenu,
+            MenuButton::EndGame,
+            MenuButton::Quit,
+        ],
+    );
+}
+
+fn spawn_results(mut commands: Commands, game_assets: Res<GameAssets>, stats: Res<GameStats>) {
+    spawn_menu(
+        &mut commands,
+        &game_assets,
+        &[
+            "Results",
+            &format!("Wood chopped: {}", stats.wood_chopped),
+            &format!("Trees felled: {}", stats.trees_felled),
+            &format!("Coins earned: {}", stats.coins_earned),
+        ],
+        &[MenuButton::MainMenu, MenuButton::Quit],
+    );
+}
+
+// spawn a full screen menu: some lines of text on top, then a column of buttons
+fn spawn_menu(
+    commands: &mut Commands,
+    game_assets: &Res<GameAssets>,
+    lines: &[&str],
+    buttons: &[MenuButton],
+) {
+    let font = game_assets.font.clone();
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            ..Default::default()
+        })
+        .insert(Menu)
+        .with_children(|parent| {
+            for line in lines {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        *line,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            }
+
+            for button in buttons {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(250.0), Val::Px(55.0)),
+                            margin: Rect::all(Val::Px(8.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        color: NORMAL_BUTTON.into(),
+                        ..Default::default()
+                    })
+                    .insert(*button)
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                button.label(),
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 30.0,
+                                    color: Color::WHITE,
+                                },
+                                Default::default(),
+                            ),
+                            ..Default::default()
+                        });
+                    });
+            }
+        });
+}
+
+fn despawn_menu(mut commands: Commands, query: Query<Entity, With<Menu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// change the state according to the clicked button
+// - 'New game' replaces the whole state stack so a running game is ended first
+// - 'Continue' pops back to the game, from the pause menu or from the main menu
+fn menu_button_action(
+    mut state: ResMut<State<AppState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut query: Query<(&Interaction, &MenuButton, &mut UiColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                let result = match button {
+                    MenuButton::NewGame => state.replace(AppState::Playing),
+                    MenuButton::Continue => state.pop(),
+                    MenuButton::MainMenu => state.set(AppState::MainMenu),
+                    MenuButton::EndGame => state.replace(AppState::GameOver),
+                    MenuButton::Quit => {
+                        app_exit_events.send(AppExit);
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    warn!("Menu: {}", e);
+                }
+            }
+            Interaction::Hovered => *color = HOVERED_BUTTON.into(),
+            Interaction::None => *color = NORMAL_BUTTON.into(),
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use crate::{
+    clock::{advance_clock, DayPhase, GameClock, Shaded, Tint},
+    depth::YSorted,
+    game_state::{AppState, GameStats, InGame},
+    interaction::{update_interactions, Interactable, Interacted, InteractionKind},
+    loading::GameAssets,
+    map::{tile_to_world, MapLayout, TILE_SIZE},
+    player::{Player, PlayerInput, PLAYER_HALF_SIZE_Y},
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{CoinResource, Item, ResourceCounter},
+    season::Season,
+    texture_atlas::AtlasHandle,
+    tick::FixedUpdateStage,
+    trading_post::TradeOffer,
+    SCALE,
+};
+use bevy::prelude::*;
+use std::fmt;
+
+pub struct MerchantPlugin;
+
+impl Plugin for MerchantPlugin {
+    fn build(&self, app: &mut App) {
+        // the catalog is read from its file with the assets, see 'GameData'
+        app.init_resource::<MerchantCatalog>()
+            .init_resource::<OpenTrade>()
+            .add_system_to_stage(FixedUpdateStage, update_merchant_visit.after(advance_clock))
+            .add_system_to_stage(
+                FixedUpdateStage,
+                trade_with_merchant
+                    .after(update_interactions)
+                    .after(update_merchant_visit),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(close_trade))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(update_trade_window),
+            );
+    }
+}
+
+// the player walking this far from the merchant closes the trade window, relative
+// to the radius to interact with it
+const TRADE_DISTANCE: f32 = 1.5;
+const MERCHANT_TINT: Color = Color::rgb(0.75, 0.6, 1.0);
+
+// A deal of the merchant, only good for the day of its visit
+#[derive(Clone)]
+pub enum Deal {
+    // the merchant buys the item from the player, like a trading post
+    Buys(TradeOffer),
+    // the merchant sells the item to the player, one at a time
+    Sells { item: Item, price: u32, stock: u32 },
+}
+
+impl Deal {
+    pub fn stock(&self) -> u32 {
+        match self {
+            Deal::Buys(offer) => offer.stock,
+            Deal::Sells { stock, .. } => *stock,
+        }
+    }
+
+    fn description(&self, season: Season) -> String {
+        match self {
+            Deal::Buys(offer) => format!(
+                "Buys {} for {} coins each ({} left)",
+                offer.item.name(),
+                offer.price(season),
+                offer.stock
+            ),
+            Deal::Sells { item, price, stock } => format!(
+                "Sells a {} for {} coins ({} left)",
+                item.name(),
+                price,
+                stock
+            ),
+        }
+    }
+}
+
+// A day of the week when the merchant comes, with what it says and its deals
+#[derive(Clone)]
+pub struct MerchantVisit {
+    pub day: u32,
+    pub greeting: String,
+    pub deals: Vec<Deal>,
+}
+
+// The visits of the merchant, they are the same every week
+pub struct MerchantCatalog {
+    week_length: u32,
+    visits: Vec<MerchantVisit>,
+}
+
+#[derive(Debug)]
+pub struct CatalogError(String);
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid merchant catalog line: '{}'", self.0)
+    }
+}
+
+// the merchant never comes, until the catalog is read from 'assets/merchant.txt'
+impl Default for MerchantCatalog {
+    fn default() -> Self {
+        MerchantCatalog {
+            week_length: 7,
+            visits: Vec::new(),
+        }
+    }
+}
+
+impl MerchantCatalog {
+    pub fn parse(text: &str) -> Result<Self, CatalogError> {
+        let mut catalog = MerchantCatalog::default();
+
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let parse_error = || CatalogError(line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            // the lines after a visit describe it
+            let visit = catalog.visits.last_mut();
+            match (&words[..], visit) {
+                (["week", days], _) => {
+                    catalog.week_length = days.parse().map_err(|_| parse_error())?;
+                    if catalog.week_length == 0 {
+                        return Err(parse_error());
+                    }
+                }
+                (["visit", day], _) => catalog.visits.push(MerchantVisit {
+                    day: day.parse().map_err(|_| parse_error())?,
+                    greeting: String::new(),
+                    deals: Vec::new(),
+                }),
+                (["greeting", ..], Some(visit)) => {
+                    visit.greeting = line["greeting".len()..].trim().to_string();
+                }
+                (["buys", item, multiplier, stock], Some(visit)) => {
+                    visit.deals.push(Deal::Buys(TradeOffer::new(
+                        Item::from_name(item).ok_or_else(parse_error)?,
+                        multiplier.parse().map_err(|_| parse_error())?,
+                        stock.parse().map_err(|_| parse_error())?,
+                    )));
+                }
+                (["sells", item, price, stock], Some(visit)) => visit.deals.push(Deal::Sells {
+                    item: Item::from_name(item).ok_or_else(parse_error)?,
+                    price: price.parse().map_err(|_| parse_error())?,
+                    stock: stock.parse().map_err(|_| parse_error())?,
+                }),
+                _ => return Err(parse_error()),
+            }
+        }
+        Ok(catalog)
+    }
+
+    // the visit of the merchant on a day of the game (the first day is 1)
+    pub fn visit_on(&self, day: u32) -> Option<&MerchantVisit> {
+        let day_of_week = (day.max(1) - 1) % self.week_length + 1;
+        self.visits.iter().find(|visit| visit.day == day_of_week)
+    }
+}
+
+// The travelling merchant, its deals lose their stock as the player trades
+#[derive(Component)]
+pub struct Merchant {
+    pub greeting: String,
+    pub deals: Vec<Deal>,
+}
+
+// The merchant the player is trading with, its window is open
+#[derive(Default)]
+pub struct OpenTrade(pub Option<Entity>);
+
+// The root node of the trade window
+#[derive(Component)]
+struct TradeWindow;
+
+fn close_trade(mut open_trade: ResMut<OpenTrade>) {
+    open_trade.0 = None;
+}
+
+// the merchant arrives at dawn on the days of its visits, and leaves at dusk
+fn update_merchant_visit(
+    clock: Res<GameClock>,
+    catalog: Res<MerchantCatalog>,
+    map_layout: Res<MapLayout>,
+    game_assets: Res<GameAssets>,
+    texture_atlas_handle: Res<AtlasHandle>,
+    mut commands: Commands,
+    merchant_query: Query<Entity, With<Merchant>>,
+) {
+    let visit = match clock.phase() {
+        DayPhase::Dawn | DayPhase::Day => catalog.visit_on(clock.day()),
+        DayPhase::Dusk | DayPhase::Night => None,
+    };
+
+    match (visit, merchant_query.iter().next()) {
+        (Some(visit), None) => {
+            let position = tile_to_world(map_layout.merchant).extend(0.0);
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    // standing, facing down
+                    sprite: TextureAtlasSprite::new(35),
+                    texture_atlas: texture_atlas_handle.0.clone(),
+                    transform: Transform::from_scale(Vec3::splat(SCALE)).with_translation(position),
+                    ..Default::default()
+                })
+                .insert(Merchant {
+                    greeting: visit.greeting.clone(),
+                    deals: visit.deals.clone(),
+                })
+                .insert(Shaded)
+                .insert(Tint(MERCHANT_TINT))
+                .insert(YSorted {
+                    anchor: -PLAYER_HALF_SIZE_Y,
+                })
+                .insert(Interactable {
+                    radius: TILE_SIZE * SCALE * 0.8,
+                    prompt: game_assets.e_key.clone(),
+                    kind: InteractionKind::Trade,
+                })
+                .insert(InGame);
+            trigger_popup(
+                &mut commands,
+                &game_assets,
+                position + Vec3::new(0.0, TILE_SIZE * SCALE, 0.0),
+                Popup::text("The merchant is in town!").with_font_size(14.0),
+            );
+        }
+        (None, Some(merchant)) => commands.entity(merchant).despawn(),
+        _ => {}
+    }
+}
+
+// - interacting with the merchant opens or closes its trade window
+// - the number keys accept a deal while the window is open
+// - the window closes when the player walks away, or when the merchant leaves
+fn trade_with_merchant(
+    clock: Res<GameClock>,
+    input: Res<PlayerInput>,
+    game_assets: Res<GameAssets>,
+    mut open_trade: ResMut<OpenTrade>,
+    mut stats: ResMut<GameStats>,
+    mut interacted_events: EventReader<Interacted>,
+    mut progress_events: EventWriter<QuestProgress>,
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    mut merchant_query: Query<(&mut Merchant, &Transform, &Interactable), Without<Player>>,
+    mut coins_res_query: Query<&mut ResourceCounter, (With<CoinResource>, Without<Item>)>,
+    mut item_res_query: Query<(&mut ResourceCounter, &Item), Without<CoinResource>>,
+) {
+    let mut open = open_trade.0;
+    for event in interacted_events.iter() {
+        if event.kind == InteractionKind::Trade {
+            open = if open == Some(event.entity) {
+                None
+            } else {
+                Some(event.entity)
+            };
+        }
+    }
+
+    let merchant = open.and_then(|entity| merchant_query.get_mut(entity).ok());
+    let player_pos = player_query
+        .get_single()
+        .map(|transform| transform.translation);
+    let (mut merchant, merchant_transform) = match (merchant, player_pos) {
+        (Some((merchant, transform, interactable)), Ok(player_pos))
+            if transform.translation.distance(player_pos)
+                <= interactable.radius * TRADE_DISTANCE =>
+        {
+            (merchant, transform)
+        }
+        _ => {
+            open = None;
+            if open_trade.0 != open {
+                open_trade.0 = open;
+            }
+            return;
+        }
+    };
+    if open_trade.0 != open {
+        open_trade.0 = open;
+    }
+
+    let deal = match input.choice {
+        Some(choice) if (choice as usize) < merchant.deals.len() => choice as usize,
+        _ => return,
+    };
+    let mut coins_count = coins_res_query.single_mut();
+    let popup_position = merchant_transform.translation + Vec3::new(0.0, TILE_SIZE * SCALE, 0.0);
+    let refusal = |text: String| {
+        Popup::text(text)
+            .with_color(Color::rgb(1.0, 0.4, 0.4))
+            .with_font_size(14.0)
+    };
+
+    let popup = match &mut merchant.deals[deal] {
+        Deal::Buys(offer) => {
+            let (mut item_count, _) = item_res_query
+                .iter_mut()
+                .find(|(_, item)| **item == offer.item)
+                .unwrap();
+            let sold = item_count.0.min(offer.stock);
+            let earned = sold * offer.price(clock.season());
+
+            if offer.stock == 0 {
+                refusal("Sold out!".to_string())
+            } else if sold == 0 {
+                refusal(format!("No {} to sell", offer.item.name()))
+            } else {
+                offer.stock -= sold;
+                item_count.0 -= sold;
+                coins_count.0 += earned;
+                stats.coins_earned += earned;
+                progress_events.send(QuestProgress::ItemSold(offer.item, sold));
+                Popup::text(format!("+{} coins", earned))
+                    .with_icon(game_assets.coin.clone())
+                    .with_color(Color::rgb(1.0, 0.85, 0.3))
+            }
+        }
+        Deal::Sells { item, price, stock } => {
+            if *stock == 0 {
+                refusal("Sold out!".to_string())
+            } else if coins_count.0 < *price {
+                refusal(format!("{} coins needed", price))
+            } else {
+                let (mut item_count, _) = item_res_query
+                    .iter_mut()
+                    .find(|(_, counted)| **counted == *item)
+                    .unwrap();
+                *stock -= 1;
+                coins_count.0 -= *price;
+                item_count.0 += 1;
+                Popup::text("+1").with_icon(item.icon(&game_assets))
+            }
+        }
+    };
+    trigger_popup(&mut commands, &game_assets, popup_position, popup);
+}
+
+// the trade window is built again when it opens, closes, or when a deal changes
+fn update_trade_window(
+    clock: Res<GameClock>,
+    game_assets: Res<GameAssets>,
+    open_trade: Res<OpenTrade>,
+    mut commands: Commands,
+    window_query: Query<Entity, With<TradeWindow>>,
+    merchant_query: Query<&Merchant>,
+    changed_query: Query<(), Changed<Merchant>>,
+) {
+    if !open_trade.is_changed() && changed_query.is_empty() {
+        return;
+    }
+    for window in window_query.iter() {
+        commands.entity(window).despawn_recursive();
+    }
+    let merchant = match open_trade
+        .0
+        .and_then(|entity| merchant_query.get(entity).ok())
+    {
+        Some(merchant) => merchant,
+        None => return,
+    };
+
+    let mut lines = vec![(format!("\"{}\"", merchant.greeting), Color::WHITE)];
+    for (i, deal) in merchant.deals.iter().enumerate() {
+        let color = if deal.stock() > 0 {
+            Color::rgb(1.0, 0.85, 0.3)
+        } else {
+            Color::GRAY
+        };
+        lines.push((
+            format!("{}. {}", i + 1, deal.description(clock.season())),
+            color,
+        ));
+    }
+    lines.push((
+        format!("Press 1-{} to trade, E to leave", merchant.deals.len()),
+        Color::GRAY,
+    ));
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(20.0),
+                    bottom: Val::Px(20.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(60.0), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(12.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(0.1, 0.07, 0.05, 0.85).into(),
+            ..Default::default()
+        })
+        .insert(TradeWindow)
+        .insert(InGame)
+        .with_children(|parent| {
+            for (line, color) in lines {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        line,
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: 20.0,
+                            color,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            }
+        });
+}
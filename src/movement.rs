@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+// How fast a character moves, in pixels per second
+#[derive(Component, Default, Clone, Copy)]
+pub struct Velocity(pub Vec2);
+
+// How fast a character reaches the velocity it wants, in pixels per second squared
+#[derive(Component)]
+pub struct Acceleration(pub f32);
+
+// How fast a character stops when it doesn't want to move anymore, in pixels per second squared
+#[derive(Component)]
+pub struct Friction(pub f32);
+
+// change the velocity toward the wanted one: speed up with the acceleration,
+// or slow down with the friction when the character wants to stop
+pub fn steer(velocity: &mut Velocity, wanted: Vec2, acceleration: f32, friction: f32, delta: f32) {
+    let rate = if wanted == Vec2::ZERO {
+        friction
+    } else {
+        acceleration
+    };
+    let difference = wanted - velocity.0;
+    let step = rate * delta;
+
+    if difference.length() <= step {
+        velocity.0 = wanted;
+    } else {
+        velocity.0 += difference.normalize() * step;
+    }
+}
+
+// move on each axis separately, so a character blocked on one axis slides along the obstacle
+// instead of stopping, the velocity is lost on the blocked axis
+pub fn move_and_slide(
+    translation: &mut Vec3,
+    velocity: &mut Velocity,
+    delta: f32,
+    is_blocked: impl Fn(Vec3) -> bool,
+) {
+    let target = *translation + Vec3::new(0.0, velocity.0.y * delta, 0.0);
+    if is_blocked(target) {
+        velocity.0.y = 0.0;
+    } else {
+        *translation = target;
+    }
+
+    let target = *translation + Vec3::new(velocity.0.x * delta, 0.0, 0.0);
+    if is_blocked(target) {
+        velocity.0.x = 0.0;
+    } else {
+        *translation = target;
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    map::{tile_to_world, world_to_tile, Map, TILE_SIZE},
+    SCALE,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+// moving to a tile on the side costs 10, on a diagonal 14 (about 10 * sqrt(2))
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+// the search gives up after this many tiles, the map is a lot smaller
+const MAX_SEARCHED_TILES: usize = 10_000;
+// how close a character must get to a waypoint before heading to the next one
+const WAYPOINT_RADIUS: f32 = 8.0;
+// how close a character must get to the end of the path to stop
+const ARRIVAL_RADIUS: f32 = 2.0;
+
+// The tiles the characters can't walk through: water, and the tiles with a tree
+pub struct Obstacles<'a> {
+    map: &'a Map,
+    trees: HashSet<IVec2>,
+}
+
+impl<'a> Obstacles<'a> {
+    pub fn new(map: &'a Map, trees: impl Iterator<Item = Vec3>) -> Self {
+        Obstacles {
+            map,
+            trees: trees
+                .map(|position| world_to_tile(position.truncate()))
+                .collect(),
+        }
+    }
+
+    pub fn is_walkable(&self, tile: IVec2) -> bool {
+        self.map.terrain_at(tile).is_walkable() && !self.trees.contains(&tile)
+    }
+}
+
+fn heuristic(from: IVec2, to: IVec2) -> u32 {
+    let distance = (to - from).abs();
+    let diagonal = distance.x.min(distance.y) as u32;
+    let straight = distance.x.max(distance.y) as u32 - diagonal;
+    diagonal * DIAGONAL_COST + straight * STRAIGHT_COST
+}
+
+// A* on the tiles, with diagonal moves that don't cut the corners of the obstacles.
+// The path goes from the tile after the start to the goal, the ties are broken on the
+// costs then on the tiles so the same path is always found
+pub fn find_path(
+    start: IVec2,
+    goal: IVec2,
+    is_walkable: impl Fn(IVec2) -> bool,
+) -> Option<Vec<IVec2>> {
+    if !is_walkable(goal) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut costs: HashMap<IVec2, u32> = HashMap::default();
+    let mut came_from: HashMap<IVec2, IVec2> = HashMap::default();
+
+    costs.insert(start, 0);
+    open.push(Reverse((heuristic(start, goal), 0, start.x, start.y)));
+
+    let mut searched = 0;
+    while let Some(Reverse((_, cost, x, y))) = open.pop() {
+        searched += 1;
+        if searched > MAX_SEARCHED_TILES {
+            return None;
+        }
+        let tile = IVec2::new(x, y);
+        if tile == goal {
+            let mut path = vec![goal];
+            while let Some(previous) = came_from.get(path.last().unwrap()) {
+                if *previous == start {
+                    break;
+                }
+                path.push(*previous);
+            }
+            if goal == start {
+                path.clear();
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // a cheaper way to this tile was already found
+        if cost > costs[&tile] {
+            continue;
+        }
+
+        for offset_y in -1..=1 {
+            for offset_x in -1..=1 {
+                let offset = IVec2::new(offset_x, offset_y);
+                let neighbor = tile + offset;
+                if offset == IVec2::ZERO || !is_walkable(neighbor) {
+                    continue;
+                }
+                let diagonal = offset_x != 0 && offset_y != 0;
+                if diagonal
+                    && (!is_walkable(tile + IVec2::new(offset_x, 0))
+                        || !is_walkable(tile + IVec2::new(0, offset_y)))
+                {
+                    continue;
+                }
+
+                let step = if diagonal {
+                    DIAGONAL_COST
+                } else {
+                    STRAIGHT_COST
+                };
+                let neighbor_cost = cost + step;
+                if costs
+                    .get(&neighbor)
+                    .map_or(true, |known| neighbor_cost < *known)
+                {
+                    costs.insert(neighbor, neighbor_cost);
+                    came_from.insert(neighbor, tile);
+                    open.push(Reverse((
+                        neighbor_cost + heuristic(neighbor, goal),
+                        neighbor_cost,
+                        neighbor.x,
+                        neighbor.y,
+                    )));
+                }
+            }
+        }
+    }
+    None
+}
+
+// the centers of the tiles of the path, the last one is replaced by where the character stops
+pub fn waypoints(path: &[IVec2], end: Vec2) -> Vec<Vec2> {
+    let mut waypoints: Vec<Vec2> = path.iter().map(|tile| tile_to_world(*tile)).collect();
+    waypoints.pop();
+    waypoints.push(end);
+    waypoints
+}
+
+// the trees are chopped from their left or right side, the closest side to the character
+// is tried first and the shortest path is kept
+pub fn path_to_tree(
+    obstacles: &Obstacles,
+    start: IVec2,
+    from_x: f32,
+    tree_position: Vec3,
+) -> Option<Vec<Vec2>> {
+    let tree_tile = world_to_tile(tree_position.truncate());
+    let chop_spots = [1, -1].map(|side| {
+        let side = side as f32 * (from_x - tree_position.x).signum();
+        (
+            tree_tile + IVec2::new(side as i32, 0),
+            tree_position.truncate() + Vec2::new(side * TILE_SIZE * SCALE * 0.5, 0.0),
+        )
+    });
+    chop_spots
+        .iter()
+        .filter_map(|(tile, spot)| {
+            find_path(start, *tile, |tile| obstacles.is_walkable(tile)).map(|path| (path, *spot))
+        })
+        .min_by_key(|(path, _)| path.len())
+        .map(|(path, spot)| waypoints(&path, spot))
+}
+
+// A character walking along a path, the waypoints are world positions
+#[derive(Component, Default)]
+pub struct PathFollower {
+    waypoints: VecDeque<Vec2>,
+}
+
+impl PathFollower {
+    pub fn follow(&mut self, waypoints: impl IntoIterator<Item = Vec2>) {
+        self.waypoints = waypoints.into_iter().collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+
+    // the velocity to follow the path, the character slows down before the end of the path
+    // to stop on it with the given deceleration
+    pub fn wanted_velocity(&mut self, position: Vec2, speed: f32, deceleration: f32) -> Vec2 {
+        while let Some(waypoint) = self.waypoints.front() {
+            let to_waypoint = *waypoint - position;
+            let distance = to_waypoint.length();
+            let last = self.waypoints.len() == 1;
+
+            if (!last && distance < WAYPOINT_RADIUS) || (last && distance < ARRIVAL_RADIUS) {
+                self.waypoints.pop_front();
+                continue;
+            }
+
+            let speed = if last {
+                speed.min((2.0 * deceleration * distance).sqrt())
+            } else {
+                speed
+            };
+            return to_waypoint / distance * speed;
+        }
+        Vec2::ZERO
+    }
+}
@@ -1,16 +1,25 @@
 use crate::{
     animations::{Animation, AnimationTimer, Animations},
-    map::{TILE_COUNT_X, TILE_COUNT_Y, TILE_SIZE},
-    resource_counter::{ResourceCounter, WoodResource},
-    sprite_popup::trigger_sprite_popup,
+    camera::MainCamera,
+    clock::Shaded,
+    depth::YSorted,
+    footsteps::Footsteps,
+    game_state::{AppState, GameStats, InGame},
+    loading::GameAssets,
+    map::{tile_to_world, world_to_tile, Map, TILE_COUNT_X, TILE_COUNT_Y, TILE_SIZE},
+    movement::{move_and_slide, steer, Acceleration, Friction, Velocity},
+    pathfinding::{find_path, path_to_tree, waypoints, Obstacles, PathFollower},
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{Item, ResourceCounter, WoodResource},
+    stamina::{Sprint, Stamina},
     texture_atlas::AtlasHandle,
-    trees::Tree,
+    tick::{FixedTick, FixedUpdateStage, Interpolated},
+    tree_feedback::TreeHit,
+    trees::{chop_tree, Sapling, Tree, TREE_ROOT_Y},
     SCALE,
 };
-use bevy::{
-    prelude::*,
-    sprite::collide_aabb::{collide, Collision},
-};
+use bevy::{input::InputSystem, prelude::*, sprite::collide_aabb::collide};
 
 // PLAYER_SIZE: Vec2 = Vec2::new(9.0, 12.0);
 
@@ -18,10 +27,17 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_player)
-            .add_system(player_movement)
-            .add_system(chop_wood_action)
-            .add_system(animate_sprite.after(player_movement));
+        app.insert_resource(PlayerInput::default())
+            .add_system_to_stage(CoreStage::PreUpdate, read_player_input.after(InputSystem))
+            .add_system_to_stage(FixedUpdateStage, plan_player_path)
+            .add_system_to_stage(FixedUpdateStage, player_movement.after(plan_player_path))
+            .add_system_to_stage(FixedUpdateStage, chop_wood_action.after(player_movement))
+            .add_system_to_stage(
+                FixedUpdateStage,
+                clear_player_actions.exclusive_system().at_end(),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_player))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(animate_sprite));
     }
 }
 
@@ -39,6 +55,32 @@ pub enum Direction {
     Right,
     Left,
 }
+
+impl Direction {
+    pub fn vector(self) -> Vec2 {
+        match self {
+            Direction::Up => Vec2::Y,
+            Direction::Down => -Vec2::Y,
+            Direction::Right => Vec2::X,
+            Direction::Left => -Vec2::X,
+        }
+    }
+
+    // the direction of the biggest move, left or right on the diagonals
+    pub fn facing(movement: Vec2) -> Direction {
+        if movement.x.abs() >= movement.y.abs() {
+            if movement.x < 0.0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            }
+        } else if movement.y < 0.0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        }
+    }
+}
 #[derive(Component)]
 pub struct Speed(pub f32);
 
@@ -54,102 +96,341 @@ pub enum ActionState {
     Recover,
     Ready,
 }
+// stamina used by each swing of the axe
+const CHOP_STAMINA_COST: f32 = 10.0;
+
 // Represent how much damage the player inflicts to a tree
 #[derive(Component)]
-pub struct Strength(u32);
+pub struct Strength(pub u32);
 
 // The Player itself
 #[derive(Component)]
 pub struct Player;
 
-// - check which direction key (Z,Q,S,D) is pressed, then compute how much to move on the x and y axis
+// What the player wants to do, read every frame and used by the next tick:
+// the directions are held keys, the actions stay set until a tick uses them
+#[derive(Default, Clone, Copy, PartialEq)]
+pub struct PlayerInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub sprint: bool,
+    pub chop: bool,
+    pub interact: bool,
+    pub plant: bool,
+    // the tile clicked to walk to it, or to chop the tree on it
+    pub move_to: Option<IVec2>,
+    // the option picked with the number keys in an open window, from 0
+    pub choice: Option<u8>,
+}
+
+impl PlayerInput {
+    fn wants_to_move(&self) -> bool {
+        self.up || self.down || self.left || self.right
+    }
+}
+
+// The tree the player walks to, then chops until it falls
+#[derive(Component)]
+pub struct ChopTarget(pub Entity);
+
+fn read_player_input(
+    keys: Res<Input<KeyCode>>,
+    mouse_btn: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    state: Res<State<AppState>>,
+    mut input: ResMut<PlayerInput>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    if *state.current() != AppState::Playing {
+        *input = PlayerInput::default();
+        return;
+    }
+
+    input.up = keys.pressed(KeyCode::Z);
+    input.down = keys.pressed(KeyCode::S);
+    input.left = keys.pressed(KeyCode::Q);
+    input.right = keys.pressed(KeyCode::D);
+    input.sprint = keys.pressed(KeyCode::LShift);
+    input.chop |= mouse_btn.just_pressed(MouseButton::Left);
+    input.interact |= keys.just_pressed(KeyCode::E);
+    input.plant |= keys.just_pressed(KeyCode::F);
+    const CHOICE_KEYS: [KeyCode; 9] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    if let Some(choice) = CHOICE_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        input.choice = Some(choice as u8);
+    }
+
+    if mouse_btn.just_pressed(MouseButton::Right) {
+        let cursor = windows.get_primary().and_then(|window| {
+            let size = Vec2::new(window.width(), window.height());
+            window.cursor_position().map(|cursor| cursor - size / 2.0)
+        });
+        if let (Some(cursor), Ok(camera_transform)) = (cursor, camera_query.get_single()) {
+            input.move_to = Some(world_to_tile(
+                camera_transform.translation.truncate() + cursor,
+            ));
+        }
+    }
+}
+
+fn clear_player_actions(mut input: ResMut<PlayerInput>) {
+    input.chop = false;
+    input.interact = false;
+    input.plant = false;
+    input.move_to = None;
+    input.choice = None;
+}
+
+// - a click on a tile finds a path to it, a click on a tree finds a path to the side of the
+//   tree to chop it
+// - moving with the keyboard stops following the path
+pub fn plan_player_path(
+    map: Res<Map>,
+    input: Res<PlayerInput>,
+    mut commands: Commands,
+    obstacle_query: Query<&Transform, (With<Tree>, Without<Player>)>,
+    tree_query: Query<(Entity, &Transform), (With<Tree>, Without<Sapling>, Without<Player>)>,
+    mut player_query: Query<
+        (Entity, &Transform, &mut PathFollower, Option<&ChopTarget>),
+        With<Player>,
+    >,
+) {
+    let (player, player_transform, mut follower, chop_target) = player_query.single_mut();
+
+    if input.wants_to_move() {
+        follower.clear();
+        commands.entity(player).remove::<ChopTarget>();
+        return;
+    }
+    // the tree was felled, or burned
+    if let Some(ChopTarget(tree)) = chop_target {
+        if tree_query.get(*tree).is_err() {
+            commands.entity(player).remove::<ChopTarget>();
+        }
+    }
+
+    let goal = match input.move_to {
+        Some(tile) => tile,
+        None => return,
+    };
+    let obstacles = Obstacles::new(
+        &map,
+        obstacle_query.iter().map(|transform| transform.translation),
+    );
+    let start = world_to_tile(player_transform.translation.truncate());
+    let tree = tree_query
+        .iter()
+        .filter(|(_, transform)| world_to_tile(transform.translation.truncate()) == goal)
+        .min_by_key(|(entity, _)| *entity);
+
+    let path = match tree {
+        Some((tree, tree_transform)) => path_to_tree(
+            &obstacles,
+            start,
+            player_transform.translation.x,
+            tree_transform.translation,
+        )
+        .map(|path| {
+            commands.entity(player).insert(ChopTarget(tree));
+            path
+        }),
+        None => find_path(start, goal, |tile| obstacles.is_walkable(tile)).map(|path| {
+            commands.entity(player).remove::<ChopTarget>();
+            waypoints(&path, tile_to_world(goal))
+        }),
+    };
+    if let Some(path) = path {
+        follower.follow(path);
+    }
+}
+
+// - check which direction is wanted (Z,Q,S,D keys), the diagonals are not faster
 // - change the player state according to the direction
-// - check if the player encounter a wall/tree and move him according to collisions
+// - speed up or slow down toward the wanted velocity, then move the player according to
+//   collisions with the walls/trees, sliding along them
 pub fn player_movement(
-    time: Res<Time>,
-    keys: Res<Input<KeyCode>>,
+    tick: Res<FixedTick>,
+    input: Res<PlayerInput>,
+    map: Res<Map>,
     tree_query: Query<&Transform, (With<Tree>, Without<Player>)>,
     mut player_query: Query<
-        (&mut Transform, &mut PlayerState, &mut Direction, &Speed),
+        (
+            &mut Transform,
+            &mut PlayerState,
+            &mut Direction,
+            &Speed,
+            &mut Velocity,
+            &Acceleration,
+            &Friction,
+            &mut Stamina,
+            Option<&Sprint>,
+            &mut PathFollower,
+            Option<&ChopTarget>,
+        ),
         With<Player>,
     >,
 ) {
-    let (mut player_transform, mut player_state, mut player_direction, player_speed) =
-        player_query.single_mut();
+    let (
+        mut player_transform,
+        mut player_state,
+        mut player_direction,
+        player_speed,
+        mut velocity,
+        acceleration,
+        friction,
+        mut stamina,
+        sprint,
+        mut follower,
+        chop_target,
+    ) = player_query.single_mut();
 
     match *player_state {
         PlayerState::Chop(_) => {}
         _ => *player_state = PlayerState::Stand(*player_direction),
     }
 
-    let mut y_delta = 0.0;
-    if keys.pressed(KeyCode::Z) {
-        y_delta += player_speed.0 * time.delta_seconds();
+    let mut wanted_direction = Vec2::ZERO;
+    if input.up {
+        wanted_direction.y += 1.0;
         *player_state = PlayerState::Move(Direction::Up);
+        *player_direction = Direction::Up;
     }
-    if keys.pressed(KeyCode::S) {
-        y_delta -= player_speed.0 * time.delta_seconds();
+    if input.down {
+        wanted_direction.y -= 1.0;
         *player_state = PlayerState::Move(Direction::Down);
+        *player_direction = Direction::Down;
     }
-
-    let mut x_delta = 0.0;
-    if keys.pressed(KeyCode::D) {
-        x_delta += player_speed.0 * time.delta_seconds();
+    // when moving diagonally, the player faces left or right
+    if input.right {
+        wanted_direction.x += 1.0;
         *player_state = PlayerState::Move(Direction::Right);
         *player_direction = Direction::Right;
     }
-    if keys.pressed(KeyCode::Q) {
-        x_delta -= player_speed.0 * time.delta_seconds();
+    if input.left {
+        wanted_direction.x -= 1.0;
         *player_state = PlayerState::Move(Direction::Left);
         *player_direction = Direction::Left;
     }
 
-    let target = player_transform.translation + Vec3::new(0.0, y_delta, 0.0);
-
-    if target.y < TILE_SIZE * SCALE * TILE_COUNT_Y as f32
-        && target.y > -(TILE_SIZE * SCALE * TILE_COUNT_Y as f32)
-        && !tree_collision(target, &tree_query)
-    {
-        player_transform.translation = target;
+    // sprinting only costs stamina while moving
+    let mut speed =
+        player_speed.0 * map.speed_multiplier(player_feet(player_transform.translation));
+    let moving = wanted_direction != Vec2::ZERO;
+    if let Some(sprint) = sprint {
+        if input.sprint && moving && !stamina.is_exhausted() {
+            speed *= sprint.speed_multiplier;
+            stamina.consume(sprint.stamina_cost * tick.delta_seconds());
+        }
     }
 
-    let target = player_transform.translation + Vec3::new(x_delta, 0.0, 0.0);
-
-    if target.x < TILE_SIZE * SCALE * TILE_COUNT_X as f32
-        && target.x > -(TILE_SIZE * SCALE * TILE_COUNT_X as f32)
-        && !tree_collision(target, &tree_query)
-    {
-        player_transform.translation = target;
+    let mut wanted_velocity = wanted_direction.normalize_or_zero() * speed;
+    if !moving && !follower.is_done() {
+        wanted_velocity =
+            follower.wanted_velocity(player_transform.translation.truncate(), speed, friction.0);
+        if wanted_velocity != Vec2::ZERO {
+            *player_direction = Direction::facing(wanted_velocity);
+            *player_state = PlayerState::Move(*player_direction);
+        }
     }
+    // face the tree to chop once next to it
+    if let Some(tree_transform) = chop_target.and_then(|target| tree_query.get(target.0).ok()) {
+        if follower.is_done() {
+            *player_direction = Direction::facing(Vec2::new(
+                tree_transform.translation.x - player_transform.translation.x,
+                0.0,
+            ));
+        }
+    }
+
+    steer(
+        &mut velocity,
+        wanted_velocity,
+        acceleration.0,
+        friction.0,
+        tick.delta_seconds(),
+    );
+    move_and_slide(
+        &mut player_transform.translation,
+        &mut velocity,
+        tick.delta_seconds(),
+        |target| {
+            target.x >= TILE_SIZE * SCALE * TILE_COUNT_X as f32
+                || target.x <= -(TILE_SIZE * SCALE * TILE_COUNT_X as f32)
+                || target.y >= TILE_SIZE * SCALE * TILE_COUNT_Y as f32
+                || target.y <= -(TILE_SIZE * SCALE * TILE_COUNT_Y as f32)
+                || !map.is_walkable(player_feet(target))
+                || tree_collision(
+                    target,
+                    tree_query.iter().map(|transform| transform.translation),
+                )
+        },
+    );
 }
 
 // match on the player action state (Ready, Perform, Recover)
-// if the state is 'Ready', check if the player collide with a tree (player_can_chop_tree)
-// if so, trigger a sprite to pop above the player, add coins to the player and then
+// if the state is 'Ready', look for the tree the player faces (tree_in_front)
+// if any, trigger a popup above the player, add wood to the player and then
 // perform the action (damage the tree)
 pub fn chop_wood_action(
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
 
-    time: Res<Time>,
-    mouse_btn: Res<Input<MouseButton>>,
+    tick: Res<FixedTick>,
+    input: Res<PlayerInput>,
+    mut stats: ResMut<GameStats>,
     mut commands: Commands,
+    mut hit_events: EventWriter<TreeHit>,
+    mut progress_events: EventWriter<QuestProgress>,
     mut player_query: Query<(
         &mut PlayerAction,
         &mut PlayerState,
         &Direction,
         &Transform,
         &Strength,
+        &mut Stamina,
+        &PathFollower,
+        Option<&ChopTarget>,
     )>,
 
-    mut tree_query: Query<(Entity, &mut Tree, &Transform)>,
+    mut tree_query: Query<(Entity, &mut Tree, &Transform), Without<Sapling>>,
     mut wood_res_query: Query<&mut ResourceCounter, With<WoodResource>>,
 ) {
-    let (mut action, mut player_state, player_direction, player_transform, player_strength) =
-        player_query.single_mut();
+    let (
+        mut action,
+        mut player_state,
+        player_direction,
+        player_transform,
+        player_strength,
+        mut stamina,
+        follower,
+        chop_target,
+    ) = player_query.single_mut();
+
+    // the player keeps chopping the clicked tree once next to it
+    let auto_chop = chop_target.map_or(false, |target| {
+        follower.is_done() && tree_query.get(target.0).is_ok()
+    });
+
+    // an exhausted player swings at half the speed
+    let swing_delta = if stamina.is_exhausted() {
+        tick.delta() / 2
+    } else {
+        tick.delta()
+    };
 
     match action.state {
         ActionState::Perform => {
-            action.action_timer.tick(time.delta());
+            action.action_timer.tick(swing_delta);
             if action.action_timer.finished() {
                 action.state = ActionState::Recover;
                 action.action_timer.reset();
@@ -158,81 +439,117 @@ pub fn chop_wood_action(
             }
         }
         ActionState::Recover => {
-            action.recover_timer.tick(time.delta());
+            action.recover_timer.tick(swing_delta);
             if action.recover_timer.finished() {
                 action.state = ActionState::Ready;
                 action.recover_timer.reset();
             }
         }
         ActionState::Ready => {
-            if mouse_btn.just_pressed(MouseButton::Left) {
+            if input.chop || auto_chop {
                 *player_state = PlayerState::Chop(*player_direction);
 
                 action.state = ActionState::Perform;
+                stamina.consume(CHOP_STAMINA_COST);
 
-                // check each tree if it can be chopped
-                for (tree_entity, mut tree_struct, tree_transform) in tree_query.iter_mut() {
-                    if player_can_chop_tree(
-                        player_transform.translation,
+                // only the closest tree in front of the player is hit
+                let target = tree_in_front(
+                    player_transform.translation,
+                    *player_direction,
+                    tree_query
+                        .iter()
+                        .map(|(entity, _, transform)| (entity, transform.translation)),
+                );
+                if let Some(tree_entity) = target {
+                    let (_, mut tree_struct, tree_transform) =
+                        tree_query.get_mut(tree_entity).unwrap();
+
+                    let mut wood_count = wood_res_query.single_mut();
+                    wood_count.0 += 1;
+                    stats.wood_chopped += 1;
+                    progress_events.send(QuestProgress::ItemCollected(Item::Wood, 1));
+
+                    trigger_popup(
+                        &mut commands,
+                        &game_assets,
+                        player_transform.translation + Vec3::new(0.5, 1.8 * TILE_SIZE, 0.0),
+                        Popup::text("+1").with_icon(game_assets.wood_log.clone()),
+                    );
+
+                    chop_tree(
+                        &mut commands,
+                        &mut hit_events,
+                        &mut progress_events,
+                        &mut stats,
+                        tree_entity,
+                        &mut tree_struct,
                         tree_transform.translation,
-                    ) {
-                        let mut wood_count = wood_res_query.single_mut();
-                        wood_count.0 += 1;
-
-                        trigger_sprite_popup(
-                            &mut commands,
-                            &asset_server,
-                            player_transform.translation + Vec3::new(0.5, 1.8 * TILE_SIZE, 0.0),
-                            SCALE * 0.5,
-                            "wood_log.png",
-                        );
-
-                        // chop the tree, inflict damage to the target tree
-                        // (move to a fn?)
-                        tree_struct.health -= player_strength.0 as i16;
-                        if tree_struct.health <= 0 {
-                            commands.entity(tree_entity).despawn();
-                        }
-                    }
+                        player_strength.0,
+                        player_transform.translation.x,
+                    );
                 }
             }
         }
     }
 }
 
-// check if the player position collide with a tree
-// return true if so, and only if it collide on the Right|Left or Inside
-fn player_can_chop_tree(player_pos: Vec3, tree_pos: Vec3) -> bool {
-    let collide = collide(
-        player_pos,
-        Vec2::new(9.0 * SCALE, 12.0 * SCALE), // player size
-        tree_pos,
-        Vec2::splat(32.0 * SCALE), // full tree sprite size
-    );
-    match collide {
-        Some(Collision::Right) | Some(Collision::Left) | Some(Collision::Inside) => true,
-        _ => false,
-    }
+// player size in the sprite: 9x12
+const PLAYER_HALF_SIZE_X: f32 = 4.5;
+pub const PLAYER_HALF_SIZE_Y: f32 = 6.0;
+// the bottom of the player sprite, where the player stands
+pub fn player_feet(player_pos: Vec3) -> Vec2 {
+    player_pos.truncate() - Vec2::new(0.0, PLAYER_HALF_SIZE_Y * SCALE)
 }
 
-// check for a collision between the player position and a tree
-pub fn tree_collision(
-    target_player_pos: Vec3,
-    tree_query: &Query<&Transform, (With<Tree>, Without<Player>)>,
-) -> bool {
-    for tree_transform in tree_query.iter() {
-        let collision = collide(
-            target_player_pos,
+// the area hit by the axe, in front of the player
+const CHOP_REACH: f32 = 16.0;
+const CHOP_WIDTH: f32 = 16.0;
+
+// the closest tree whose trunk is in the axe hitbox, projected in front of the player
+fn tree_in_front(
+    player_pos: Vec3,
+    direction: Direction,
+    trees: impl Iterator<Item = (Entity, Vec3)>,
+) -> Option<Entity> {
+    let forward = direction.vector();
+    let (half_extent, size) = if forward.x != 0.0 {
+        (PLAYER_HALF_SIZE_X, Vec2::new(CHOP_REACH, CHOP_WIDTH))
+    } else {
+        (PLAYER_HALF_SIZE_Y, Vec2::new(CHOP_WIDTH, CHOP_REACH))
+    };
+    let hitbox = player_pos + (forward * (half_extent + CHOP_REACH / 2.0) * SCALE).extend(0.0);
+
+    trees
+        .map(|(entity, tree_pos)| (entity, tree_pos + Vec3::new(0.0, -6.0 * SCALE, 0.0)))
+        .filter(|(_, trunk)| {
+            collide(
+                hitbox,
+                size * SCALE,
+                *trunk,
+                Vec2::new(12.0, 20.0) * SCALE, // the trunk, from the root to the leaves
+            )
+            .is_some()
+        })
+        .min_by(|(a, a_trunk), (b, b_trunk)| {
+            let a_distance = a_trunk.truncate().distance_squared(player_pos.truncate());
+            let b_distance = b_trunk.truncate().distance_squared(player_pos.truncate());
+            a_distance.total_cmp(&b_distance).then(a.cmp(b))
+        })
+        .map(|(entity, _)| entity)
+}
+
+// check for a collision between a character position and the trees
+pub fn tree_collision(target_pos: Vec3, mut trees: impl Iterator<Item = Vec3>) -> bool {
+    trees.any(|tree_pos| {
+        collide(
+            target_pos,
             Vec2::new(9.0 * SCALE, 12.0 * SCALE), // character real size: 9x12
-            tree_transform.translation + Vec3::new(0.0, -11.0 * SCALE, 0.0), // collide only with
-            // the tree root
+            // collide only with the tree root
+            tree_pos + Vec3::new(0.0, TREE_ROOT_Y * SCALE, 0.0),
             Vec2::new(12.0 * SCALE, 5.0 * SCALE), // adjust the tree size to match only the root
-        );
-        if collision.is_some() {
-            return true;
-        }
-    }
-    false
+        )
+        .is_some()
+    })
 }
 
 // spawn the player with the texture atlas for animations, scale him, and increase the z axis
@@ -241,13 +558,29 @@ fn spawn_player(mut commands: Commands, texture_atlas_handle: Res<AtlasHandle>)
     commands
         .spawn_bundle(SpriteSheetBundle {
             texture_atlas: texture_atlas_handle.clone(),
-            transform: Transform::from_scale(Vec3::splat(SCALE))
-                .with_translation(Vec3::new(0.0, 0.0, 10.0)),
+            transform: Transform::from_scale(Vec3::splat(SCALE)).with_translation(Vec3::ZERO),
             ..Default::default()
         })
         .insert(Player)
+        .insert(Shaded)
+        .insert(InGame)
+        .insert(Interpolated::new(Vec3::ZERO))
+        .insert(YSorted {
+            anchor: -PLAYER_HALF_SIZE_Y,
+        })
         .insert(Strength(40))
         .insert(Speed(3.0 * TILE_SIZE * SCALE))
+        .insert(Velocity::default())
+        .insert(PathFollower::default())
+        // full speed or stopped in about a tenth of a second
+        .insert(Acceleration(30.0 * TILE_SIZE * SCALE))
+        .insert(Friction(30.0 * TILE_SIZE * SCALE))
+        .insert(Footsteps::new(player_feet(Vec3::ZERO)))
+        .insert(Stamina::new(100.0))
+        .insert(Sprint {
+            speed_multiplier: 1.6,
+            stamina_cost: 20.0,
+        })
         .insert(PlayerState::Stand(Direction::Right))
         .insert(Direction::Right)
         .insert(PlayerAction {
@@ -255,72 +588,66 @@ fn spawn_player(mut commands: Commands, texture_atlas_handle: Res<AtlasHandle>)
             action_timer: Timer::from_seconds(0.2, false),
             recover_timer: Timer::from_seconds(0.2, false),
         })
-        .insert(Animations {
-            animations: vec![
-                // index 0: running->right
-                Animation {
-                    frames: vec![2, 3],
-                    current_frame: 0,
-                    timer: AnimationTimer(Timer::from_seconds(0.2, true)),
-                },
-                // index 1: running->left
-                Animation {
-                    frames: vec![7, 8],
-                    current_frame: 0,
-                    timer: AnimationTimer(Timer::from_seconds(0.2, true)),
-                },
-                // index 2: running->up/down right
-                Animation {
-                    frames: vec![10, 11],
-                    current_frame: 0,
-                    timer: AnimationTimer(Timer::from_seconds(0.2, true)),
-                },
-                // index 3: running->up/down left
-                Animation {
-                    frames: vec![12, 13],
-                    current_frame: 0,
-                    timer: AnimationTimer(Timer::from_seconds(0.2, true)),
-                },
-            ],
-        });
+        .insert(character_animations());
 }
 
-// Animate the player sprite according to the state and direction
+// the walking animations of the player and the characters using its sprites,
+// the index is the direction (see 'animate_sprite')
+pub fn character_animations() -> Animations {
+    Animations {
+        animations: vec![
+            // index 0: running->right
+            Animation {
+                frames: vec![2, 3],
+                current_frame: 0,
+                timer: AnimationTimer(Timer::from_seconds(0.2, true)),
+            },
+            // index 1: running->left
+            Animation {
+                frames: vec![7, 8],
+                current_frame: 0,
+                timer: AnimationTimer(Timer::from_seconds(0.2, true)),
+            },
+            // index 2: running->up
+            Animation {
+                frames: vec![42, 43],
+                current_frame: 0,
+                timer: AnimationTimer(Timer::from_seconds(0.2, true)),
+            },
+            // index 3: running->down
+            Animation {
+                frames: vec![37, 38],
+                current_frame: 0,
+                timer: AnimationTimer(Timer::from_seconds(0.2, true)),
+            },
+        ],
+    }
+}
+
+// Animate the characters sprite according to their state and direction
 fn animate_sprite(
     time: Res<Time>,
-    mut query: Query<
-        (
-            &PlayerState,
-            &Direction,
-            &mut TextureAtlasSprite,
-            &mut Animations,
-        ),
-        With<Player>,
-    >,
+    mut query: Query<(&PlayerState, &mut TextureAtlasSprite, &mut Animations)>,
 ) {
-    for (player_state, player_direction, mut sprite, mut animations) in query.iter_mut() {
+    for (player_state, mut sprite, mut animations) in query.iter_mut() {
         match *player_state {
-            PlayerState::Move(Direction::Right) => {
-                let animation = &mut animations.animations[0];
-                animation.update(&time, &mut sprite);
-            }
-            PlayerState::Move(Direction::Left) => {
-                let animation = &mut animations.animations[1];
-                animation.update(&time, &mut sprite);
-            }
-            PlayerState::Move(Direction::Up) | PlayerState::Move(Direction::Down) => {
-                let animation = match player_direction {
-                    Direction::Right => &mut animations.animations[2],
-                    Direction::Left => &mut animations.animations[3],
-                    _ => panic!("Player: Unexpected direction"),
+            PlayerState::Move(direction) => {
+                let animation = match direction {
+                    Direction::Right => &mut animations.animations[0],
+                    Direction::Left => &mut animations.animations[1],
+                    Direction::Up => &mut animations.animations[2],
+                    Direction::Down => &mut animations.animations[3],
                 };
                 animation.update(&time, &mut sprite);
             }
             PlayerState::Chop(Direction::Right) => sprite.index = 1,
             PlayerState::Chop(Direction::Left) => sprite.index = 6,
+            PlayerState::Chop(Direction::Up) => sprite.index = 41,
+            PlayerState::Chop(Direction::Down) => sprite.index = 36,
             PlayerState::Stand(Direction::Right) => sprite.index = 0,
             PlayerState::Stand(Direction::Left) => sprite.index = 5,
-            _ => {}
+            PlayerState::Stand(Direction::Up) => sprite.index = 40,
+            PlayerState::Stand(Direction::Down) => sprite.index = 35,
         }
     }
 }
@@ -0,0 +1,220 @@
+use crate::{
+    depth::OVERLAY_Z,
+    game_state::InGame,
+    loading::GameAssets,
+    map::TILE_SIZE,
+    player,
+    tick::{FixedTick, FixedUpdateStage, Interpolated},
+    SCALE,
+};
+use bevy::prelude::*;
+
+pub struct PopupPlugin;
+
+impl Plugin for PopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            FixedUpdateStage,
+            update_popups.after(player::chop_wood_action),
+        );
+    }
+}
+
+const POPUP_SECONDS: f32 = 0.9;
+// how high the popup rises during its life
+const POPUP_RISE: f32 = 1.5 * TILE_SIZE * SCALE;
+// the popup fades during the last part of its life
+const POPUP_FADE_START: f32 = 0.5;
+// popups closer than this are stacked on top of each other
+const STACK_RADIUS: f32 = TILE_SIZE * SCALE;
+const STACK_SPACING: f32 = 6.0;
+// how fast a popup moves to its place in the stack
+const STACK_SPEED: f32 = 12.0;
+const ICON_GAP: f32 = 4.0;
+
+// What a popup shows: a text, an icon, or both (the icon on the left of the text)
+pub struct Popup {
+    text: Option<String>,
+    icon: Option<Handle<Image>>,
+    color: Color,
+    font_size: f32,
+}
+
+impl Default for Popup {
+    fn default() -> Self {
+        Popup {
+            text: None,
+            icon: None,
+            color: Color::WHITE,
+            font_size: 18.0,
+        }
+    }
+}
+
+impl Popup {
+    pub fn text(text: impl Into<String>) -> Self {
+        Popup {
+            text: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn icon(texture: Handle<Image>) -> Self {
+        Popup {
+            icon: Some(texture),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_icon(mut self, texture: Handle<Image>) -> Self {
+        self.icon = Some(texture);
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+}
+
+#[derive(Component)]
+struct PopupState {
+    timer: Timer,
+    // where the popup was triggered, before rising and stacking
+    origin: Vec3,
+    stack_offset: f32,
+    height: f32,
+    color: Color,
+}
+
+#[derive(Component)]
+struct PopupIcon;
+
+// the popups are in the overlay layer, whatever the z of the position
+pub fn trigger_popup(commands: &mut Commands, game_assets: &GameAssets, pos: Vec3, popup: Popup) {
+    let pos = pos.truncate().extend(OVERLAY_Z);
+    // the text size is not known before its layout, guess it from the font
+    let text_width = popup.text.as_ref().map_or(0.0, |text| {
+        text.chars().count() as f32 * popup.font_size * 0.5
+    });
+    let icon_size = popup.font_size * 1.2;
+    let icon_width = popup.icon.as_ref().map_or(0.0, |_| icon_size + ICON_GAP);
+    let left = -(text_width + icon_width) / 2.0;
+
+    commands
+        .spawn_bundle(TransformBundle::from_transform(
+            Transform::from_translation(pos),
+        ))
+        .insert(PopupState {
+            timer: Timer::from_seconds(POPUP_SECONDS, false),
+            origin: pos,
+            stack_offset: 0.0,
+            height: popup.font_size.max(icon_size),
+            color: popup.color,
+        })
+        .insert(Interpolated::new(pos))
+        .insert(InGame)
+        .with_children(|parent| {
+            if let Some(icon) = popup.icon {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(icon_size)),
+                            ..Default::default()
+                        },
+                        texture: icon,
+                        transform: Transform::from_xyz(left + icon_size / 2.0, 0.0, 0.0),
+                        ..Default::default()
+                    })
+                    .insert(PopupIcon);
+            }
+            if let Some(text) = popup.text {
+                parent.spawn_bundle(Text2dBundle {
+                    text: Text::with_section(
+                        text,
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: popup.font_size,
+                            color: popup.color,
+                        },
+                        TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Left,
+                        },
+                    ),
+                    transform: Transform::from_xyz(left + icon_width, 0.0, 0.0),
+                    ..Default::default()
+                });
+            }
+        });
+}
+
+fn ease_out_cubic(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+fn ease_in_quad(t: f32) -> f32 {
+    t * t
+}
+
+// rise and fade the popups, the newer ones push the older ones at the same place upward
+fn update_popups(
+    tick: Res<FixedTick>,
+    mut commands: Commands,
+    mut popup_query: Query<(Entity, &mut PopupState, &mut Transform, &Children)>,
+    mut icon_query: Query<&mut Sprite, With<PopupIcon>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let mut popups: Vec<(Entity, Vec3, f32)> = Vec::new();
+    for (entity, mut state, _, _) in popup_query.iter_mut() {
+        state.timer.tick(tick.delta());
+        if state.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            popups.push((entity, state.origin, state.timer.elapsed_secs()));
+        }
+    }
+
+    for (entity, origin, elapsed) in popups.iter() {
+        // the popup is pushed up by the newer ones spawned close to it
+        let newer_popups = popups
+            .iter()
+            .filter(|(other, other_origin, other_elapsed)| {
+                (*other_elapsed, *other) < (*elapsed, *entity)
+                    && other_origin.truncate().distance(origin.truncate()) < STACK_RADIUS
+            })
+            .count();
+
+        let (_, mut state, mut transform, children) = popup_query.get_mut(*entity).unwrap();
+        let target_offset = newer_popups as f32 * (state.height + STACK_SPACING);
+        state.stack_offset +=
+            (target_offset - state.stack_offset) * (STACK_SPEED * tick.delta_seconds()).min(1.0);
+
+        let progress = state.timer.percent();
+        let fade = ((progress - POPUP_FADE_START) / (1.0 - POPUP_FADE_START)).max(0.0);
+        let alpha = 1.0 - ease_in_quad(fade);
+
+        transform.translation = state.origin
+            + Vec3::new(
+                0.0,
+                POPUP_RISE * ease_out_cubic(progress) + state.stack_offset,
+                0.0,
+            );
+
+        for child in children.iter() {
+            if let Ok(mut sprite) = icon_query.get_mut(*child) {
+                sprite.color.set_a(alpha);
+            }
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                for section in text.sections.iter_mut() {
+                    section.style.color = *state.color.clone().set_a(alpha);
+                }
+            }
+        }
+    }
+}
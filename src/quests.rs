@@ -0,0 +1,479 @@
+use crate::{
+    clock::GameClock,
+    dialogue::Carried,
+    game_state::{AppState, GameStats, InGame},
+    loading::GameAssets,
+    map::TILE_SIZE,
+    player::Player,
+    popup::{trigger_popup, Popup},
+    resource_counter::{CoinResource, Item, ResourceCounter},
+    tick::FixedUpdateStage,
+};
+use bevy::prelude::*;
+use std::{fmt, time::Duration};
+
+pub struct QuestPlugin;
+
+impl Plugin for QuestPlugin {
+    fn build(&self, app: &mut App) {
+        // the catalog is read from its file with the assets, see 'GameData'
+        app.init_resource::<QuestCatalog>()
+            .init_resource::<QuestLog>()
+            .init_resource::<ShowQuestLog>()
+            .add_event::<StartQuest>()
+            .add_event::<QuestProgress>()
+            // after every system sending progress, so it is counted on the same tick
+            .add_system_to_stage(FixedUpdateStage, update_quests.exclusive_system().at_end())
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_quests))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(toggle_quest_log)
+                    .with_system(update_quest_log_window.after(toggle_quest_log)),
+            );
+    }
+}
+
+const QUEST_LOG_KEY: KeyCode = KeyCode::J;
+
+// Sent for the objectives of the quests to count what the player does
+#[derive(Debug, Clone, Copy)]
+pub enum QuestProgress {
+    TreeFelled,
+    ItemCollected(Item, u32),
+    ItemSold(Item, u32),
+}
+
+// Sent to take a quest of the catalog, by name
+pub struct StartQuest(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    Fell(u32),
+    Collect(Item, u32),
+    Sell(Item, u32),
+}
+
+impl Objective {
+    pub fn amount(self) -> u32 {
+        match self {
+            Objective::Fell(amount)
+            | Objective::Collect(_, amount)
+            | Objective::Sell(_, amount) => amount,
+        }
+    }
+
+    // how much the progress counts for the objective
+    fn counted(self, progress: QuestProgress) -> u32 {
+        match (self, progress) {
+            (Objective::Fell(_), QuestProgress::TreeFelled) => 1,
+            (Objective::Collect(item, _), QuestProgress::ItemCollected(collected, amount))
+            | (Objective::Sell(item, _), QuestProgress::ItemSold(collected, amount))
+                if item == collected =>
+            {
+                amount
+            }
+            _ => 0,
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Objective::Fell(_) => "Fell trees".to_string(),
+            Objective::Collect(item, _) => format!("Collect {}", item.name()),
+            Objective::Sell(item, _) => format!("Sell {}", item.name()),
+        }
+    }
+}
+
+// A quest of the catalog, see 'assets/quests.txt' for its format
+#[derive(Debug, Clone)]
+pub struct QuestData {
+    pub name: String,
+    pub title: String,
+    pub days: Option<u32>,
+    pub objectives: Vec<Objective>,
+    pub rewards: Vec<(Carried, u32)>,
+}
+
+// The quests of 'assets/quests.txt', see the file for its format
+#[derive(Default)]
+pub struct QuestCatalog {
+    quests: Vec<QuestData>,
+}
+
+// the quest names and the dialogue flags are words of the save file: lowercase letters,
+// digits and underscores
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[derive(Debug)]
+pub struct QuestError(String);
+
+impl fmt::Display for QuestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid quest line: '{}'", self.0)
+    }
+}
+
+impl QuestCatalog {
+    pub fn parse(text: &str) -> Result<Self, QuestError> {
+        let mut catalog = QuestCatalog::default();
+
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let parse_error = || QuestError(line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let item = |name: &str| Item::from_name(name).ok_or_else(parse_error);
+            let amount = |amount: &str| amount.parse::<u32>().map_err(|_| parse_error());
+
+            // the lines after a quest describe it
+            let quest = catalog.quests.last_mut();
+            match (&words[..], quest) {
+                (["quest", name], _) if is_valid_name(name) => catalog.quests.push(QuestData {
+                    name: name.to_string(),
+                    title: name.to_string(),
+                    days: None,
+                    objectives: Vec::new(),
+                    rewards: Vec::new(),
+                }),
+                (["title", _, ..], Some(quest)) => quest.title = words[1..].join(" "),
+                (["days", days], Some(quest)) => quest.days = Some(amount(days)?),
+                (["fell", trees], Some(quest)) => {
+                    quest.objectives.push(Objective::Fell(amount(trees)?))
+                }
+                (["collect", name, count], Some(quest)) => quest
+                    .objectives
+                    .push(Objective::Collect(item(name)?, amount(count)?)),
+                (["sell", name, count], Some(quest)) => quest
+                    .objectives
+                    .push(Objective::Sell(item(name)?, amount(count)?)),
+                (["reward", name, count], Some(quest)) => quest.rewards.push((
+                    Carried::from_name(name).ok_or_else(parse_error)?,
+                    amount(count)?,
+                )),
+                _ => return Err(parse_error()),
+            }
+        }
+        Ok(catalog)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&QuestData> {
+        self.quests.iter().find(|quest| quest.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuestStatus {
+    Active,
+    Completed,
+    Failed,
+}
+
+// A quest taken by the player, with the progress of each objective and the time of
+// its deadline on the game clock
+pub struct Quest {
+    data: QuestData,
+    progress: Vec<u32>,
+    deadline: Option<Duration>,
+    status: QuestStatus,
+}
+
+impl Quest {
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+
+    pub fn title(&self) -> &str {
+        &self.data.title
+    }
+
+    pub fn status(&self) -> QuestStatus {
+        self.status
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    // the objectives, with how much of them is done
+    pub fn objectives(&self) -> impl Iterator<Item = (Objective, u32)> + '_ {
+        self.data
+            .objectives
+            .iter()
+            .copied()
+            .zip(self.progress.iter().copied())
+    }
+
+    fn is_done(&self) -> bool {
+        self.objectives()
+            .all(|(objective, progress)| progress >= objective.amount())
+    }
+}
+
+// The quests taken during the game, in the order they were taken
+#[derive(Default)]
+pub struct QuestLog(Vec<Quest>);
+
+impl QuestLog {
+    pub fn iter(&self) -> impl Iterator<Item = &Quest> {
+        self.0.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Quest> {
+        self.0.iter().find(|quest| quest.name() == name)
+    }
+
+    // add a quest of a save file, its progress must have a value per objective
+    pub fn restore(
+        &mut self,
+        data: QuestData,
+        progress: Vec<u32>,
+        deadline: Option<Duration>,
+        status: QuestStatus,
+    ) {
+        debug_assert_eq!(progress.len(), data.objectives.len());
+        self.0.retain(|quest| quest.name() != data.name);
+        self.0.push(Quest {
+            data,
+            progress,
+            deadline,
+            status,
+        });
+    }
+}
+
+// Whether the quest log window is shown, it is not part of the game
+#[derive(Default)]
+pub struct ShowQuestLog(pub bool);
+
+// The root node of the quest log window
+#[derive(Component)]
+struct QuestLogWindow;
+
+pub fn reset_quests(mut quest_log: ResMut<QuestLog>) {
+    *quest_log = QuestLog::default();
+}
+
+// - taking a quest starts its clock, a quest already going on can't be taken again
+// - the progress counts for the active quests only
+// - a quest is done when all its objectives are reached, its rewards are given right away
+// - a quest fails when its deadline passes
+fn update_quests(
+    clock: Res<GameClock>,
+    catalog: Res<QuestCatalog>,
+    game_assets: Res<GameAssets>,
+    mut quest_log: ResMut<QuestLog>,
+    mut stats: ResMut<GameStats>,
+    mut start_events: EventReader<StartQuest>,
+    mut progress_events: EventReader<QuestProgress>,
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    mut counter_query: Query<
+        (&mut ResourceCounter, Option<&Item>),
+        Or<(With<CoinResource>, With<Item>)>,
+    >,
+) {
+    let started: Vec<&StartQuest> = start_events.iter().collect();
+    let progress: Vec<QuestProgress> = progress_events.iter().copied().collect();
+    let now = clock.elapsed();
+    let active: Vec<&Quest> = quest_log
+        .iter()
+        .filter(|quest| quest.status == QuestStatus::Active)
+        .collect();
+    let expired = active
+        .iter()
+        .any(|quest| quest.deadline.map_or(false, |deadline| now >= deadline));
+    let counted = !progress.is_empty() && !active.is_empty();
+    // the quest log is only changed when something happens to the quests
+    if started.is_empty() && !counted && !expired {
+        return;
+    }
+
+    let popup_position = player_query
+        .get_single()
+        .map_or(Vec3::ZERO, |transform| transform.translation)
+        + Vec3::new(0.0, 2.2 * TILE_SIZE, 0.0);
+    let mut popups = Vec::new();
+
+    for StartQuest(name) in started {
+        let data = match catalog.get(name) {
+            Some(data) => data,
+            None => {
+                warn!("Unknown quest: {}", name);
+                continue;
+            }
+        };
+        let active = quest_log
+            .get(name)
+            .map_or(false, |quest| quest.status == QuestStatus::Active);
+        if active {
+            continue;
+        }
+        quest_log.0.retain(|quest| quest.name() != name);
+        quest_log.0.push(Quest {
+            data: data.clone(),
+            progress: vec![0; data.objectives.len()],
+            deadline: data.days.map(|days| now + clock.day_length() * days),
+            status: QuestStatus::Active,
+        });
+        popups.push(
+            Popup::text(format!("New contract: {}", data.title))
+                .with_color(Color::rgb(1.0, 0.85, 0.3))
+                .with_font_size(14.0),
+        );
+    }
+
+    for quest in quest_log.0.iter_mut() {
+        if quest.status != QuestStatus::Active {
+            continue;
+        }
+        for (objective, done) in quest.data.objectives.iter().zip(quest.progress.iter_mut()) {
+            let counted: u32 = progress.iter().map(|event| objective.counted(*event)).sum();
+            *done = (*done + counted).min(objective.amount());
+        }
+
+        if quest.is_done() {
+            quest.status = QuestStatus::Completed;
+            for (carried, amount) in quest.data.rewards.iter() {
+                for (mut counter, item) in counter_query.iter_mut() {
+                    if carried.counts(item) {
+                        counter.0 += amount;
+                    }
+                }
+                if *carried == Carried::Coins {
+                    stats.coins_earned += amount;
+                }
+            }
+            popups.push(
+                Popup::text(format!("Contract done: {}", quest.data.title))
+                    .with_icon(game_assets.coin.clone())
+                    .with_color(Color::rgb(1.0, 0.85, 0.3)),
+            );
+        } else if quest.deadline.map_or(false, |deadline| now >= deadline) {
+            quest.status = QuestStatus::Failed;
+            popups.push(
+                Popup::text(format!("Contract failed: {}", quest.data.title))
+                    .with_color(Color::rgb(1.0, 0.4, 0.4))
+                    .with_font_size(14.0),
+            );
+        }
+    }
+
+    // the popups of the same tick are stacked
+    for (i, popup) in popups.into_iter().enumerate() {
+        let position = popup_position + Vec3::new(0.0, i as f32 * 0.6 * TILE_SIZE, 0.0);
+        trigger_popup(&mut commands, &game_assets, position, popup);
+    }
+}
+
+fn toggle_quest_log(keys: Res<Input<KeyCode>>, mut show_quest_log: ResMut<ShowQuestLog>) {
+    if keys.just_pressed(QUEST_LOG_KEY) {
+        show_quest_log.0 = !show_quest_log.0;
+    }
+}
+
+// the day and the hour of a time on the game clock
+fn clock_time(clock: &GameClock, time: Duration) -> String {
+    let days = time.as_secs_f32() / clock.day_length().as_secs_f32();
+    let hours = (days.fract() * 24.0) as u32;
+    format!("day {}, {:02}:00", days as u32 + 1, hours)
+}
+
+// the window is built again when it is shown or hidden, or when a quest changes
+fn update_quest_log_window(
+    clock: Res<GameClock>,
+    game_assets: Res<GameAssets>,
+    quest_log: Res<QuestLog>,
+    show_quest_log: Res<ShowQuestLog>,
+    mut commands: Commands,
+    window_query: Query<Entity, With<QuestLogWindow>>,
+) {
+    if !quest_log.is_changed() && !show_quest_log.is_changed() {
+        return;
+    }
+    for window in window_query.iter() {
+        commands.entity(window).despawn_recursive();
+    }
+    if !show_quest_log.0 {
+        return;
+    }
+
+    let mut lines = vec![("Contracts".to_string(), Color::WHITE, 22.0)];
+    for quest in quest_log.iter() {
+        let (state, color) = match (quest.status, quest.deadline) {
+            (QuestStatus::Active, Some(deadline)) => (
+                format!("due {}", clock_time(&clock, deadline)),
+                Color::rgb(1.0, 0.85, 0.3),
+            ),
+            (QuestStatus::Active, None) => ("no deadline".to_string(), Color::rgb(1.0, 0.85, 0.3)),
+            (QuestStatus::Completed, _) => ("done".to_string(), Color::rgb(0.5, 0.9, 0.5)),
+            (QuestStatus::Failed, _) => ("failed".to_string(), Color::rgb(1.0, 0.4, 0.4)),
+        };
+        lines.push((format!("{} ({})", quest.title(), state), color, 18.0));
+        for (objective, done) in quest.objectives() {
+            lines.push((
+                format!(
+                    "  {}: {}/{}",
+                    objective.description(),
+                    done,
+                    objective.amount()
+                ),
+                Color::GRAY,
+                16.0,
+            ));
+        }
+    }
+    if quest_log.iter().next().is_none() {
+        lines.push((
+            "No contract, see the notice board".to_string(),
+            Color::GRAY,
+            16.0,
+        ));
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(20.0),
+                    top: Val::Px(20.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(12.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(0.1, 0.07, 0.05, 0.85).into(),
+            ..Default::default()
+        })
+        .insert(QuestLogWindow)
+        .insert(InGame)
+        .with_children(|parent| {
+            for (line, color, font_size) in lines {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(3.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        line,
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size,
+                            color,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            }
+        });
+}
@@ -0,0 +1,579 @@
+use crate::{
+    clock::GameClock,
+    dialogue::{DialogueFlags, OpenDialogue},
+    fire::{Burning, CharredStump},
+    game_state::{reset_game_rng, AppState, GameRng, GameStats},
+    headless::headless_app,
+    merchant::{Merchant, OpenTrade},
+    movement::Velocity,
+    player::{Player, PlayerInput},
+    quests::QuestLog,
+    resource_counter::{
+        CharcoalResource, CoinResource, ResourceCounter, SaplingResource, WoodResource,
+    },
+    stamina::Stamina,
+    tick::{FixedUpdateStage, Interpolated},
+    trading_post::TradingPost,
+    trees::{Sapling, Tree},
+    weather::{FallenLog, Weather},
+    wildlife::{Animal, AnimalKind, AnimalState},
+    workers::{Worker, WorkerState},
+};
+use bevy::{app::AppExit, ecs::event::Events, prelude::*};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            FixedUpdateStage,
+            feed_replay_input.exclusive_system().at_start(),
+        )
+        .add_system_to_stage(FixedUpdateStage, record_input.exclusive_system().at_start())
+        .add_system_to_stage(
+            CoreStage::Last,
+            save_recording_on_app_exit.exclusive_system(),
+        )
+        .add_system_set(
+            SystemSet::on_enter(AppState::Playing)
+                .with_system(start_recording.after(reset_game_rng)),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Playing)
+                .with_system(save_recording.exclusive_system().at_start()),
+        );
+    }
+}
+
+// A recorded game session: the seed of the game and the player input of every tick,
+// with the hash of the game state at the end of the session to check the replay against
+#[derive(Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub inputs: Vec<PlayerInput>,
+    pub hash: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse(String),
+    MissingHash,
+    Mismatch { expected: u64, found: u64 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Parse(line) => write!(f, "invalid replay line: '{}'", line),
+            ReplayError::MissingHash => write!(f, "the replay has no state hash to check"),
+            ReplayError::Mismatch { expected, found } => write!(
+                f,
+                "state hash mismatch: expected {:016x}, found {:016x}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+// The replay file is a text file:
+//   seed <seed>
+//   hash <state hash, in hex>
+//   <input bits> <amount of ticks> [<move to tile x> <move to tile y>] [choice <choice>]
+//   (one line per run of identical inputs)
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        if let Some(hash) = self.hash {
+            writeln!(f, "hash {:016x}", hash)?;
+        }
+
+        let mut inputs = self.inputs.iter();
+        if let Some(mut current) = inputs.next() {
+            let mut count = 1;
+            for input in inputs {
+                if input == current {
+                    count += 1;
+                } else {
+                    write_input(f, current, count)?;
+                    current = input;
+                    count = 1;
+                }
+            }
+            write_input(f, current, count)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_input(f: &mut fmt::Formatter, input: &PlayerInput, count: usize) -> fmt::Result {
+    write!(f, "{} {}", input_to_bits(*input), count)?;
+    if let Some(tile) = input.move_to {
+        write!(f, " {} {}", tile.x, tile.y)?;
+    }
+    if let Some(choice) = input.choice {
+        write!(f, " choice {}", choice)?;
+    }
+    writeln!(f)
+}
+
+impl Replay {
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut replay = Replay::default();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let parse_error = || ReplayError::Parse(line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            match words[..] {
+                ["seed", seed] => replay.seed = seed.parse().map_err(|_| parse_error())?,
+                ["hash", hash] => {
+                    replay.hash = Some(u64::from_str_radix(hash, 16).map_err(|_| parse_error())?)
+                }
+                [bits, count, ref rest @ ..] => {
+                    let bits: u8 = bits.parse().map_err(|_| parse_error())?;
+                    let count: usize = count.parse().map_err(|_| parse_error())?;
+                    let (move_to, choice) = match rest {
+                        [] => (None, None),
+                        ["choice", choice] => (None, Some(choice)),
+                        [x, y] => (Some((x, y)), None),
+                        [x, y, "choice", choice] => (Some((x, y)), Some(choice)),
+                        _ => return Err(parse_error()),
+                    };
+                    let mut input = input_from_bits(bits);
+                    if let Some((x, y)) = move_to {
+                        input.move_to = Some(IVec2::new(
+                            x.parse().map_err(|_| parse_error())?,
+                            y.parse().map_err(|_| parse_error())?,
+                        ));
+                    }
+                    if let Some(choice) = choice {
+                        input.choice = Some(choice.parse().map_err(|_| parse_error())?);
+                    }
+                    let len = replay.inputs.len();
+                    replay.inputs.resize(len + count, input);
+                }
+                _ => return Err(parse_error()),
+            }
+        }
+        Ok(replay)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Replay::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+}
+
+fn input_to_bits(input: PlayerInput) -> u8 {
+    [
+        input.up,
+        input.down,
+        input.left,
+        input.right,
+        input.chop,
+        input.interact,
+        input.sprint,
+        input.plant,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, set)| bits | ((*set as u8) << i))
+}
+
+fn input_from_bits(bits: u8) -> PlayerInput {
+    let bit = |i: u8| bits & (1 << i) != 0;
+    PlayerInput {
+        up: bit(0),
+        down: bit(1),
+        left: bit(2),
+        right: bit(3),
+        chop: bit(4),
+        interact: bit(5),
+        sprint: bit(6),
+        plant: bit(7),
+        move_to: None,
+        choice: None,
+    }
+}
+
+// Record the player input of every tick, the recording starts with each new game
+// and is written to the file when the game ends or the app is closed
+pub struct InputRecorder {
+    path: PathBuf,
+    replay: Replay,
+    recording: bool,
+}
+
+impl InputRecorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        InputRecorder {
+            path: path.into(),
+            replay: Replay::default(),
+            recording: false,
+        }
+    }
+
+    // the replay recorded so far, without the state hash
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+fn start_recording(game_rng: Res<GameRng>, recorder: Option<ResMut<InputRecorder>>) {
+    if let Some(mut recorder) = recorder {
+        recorder.replay = Replay {
+            seed: game_rng.seed(),
+            ..Default::default()
+        };
+        recorder.recording = true;
+    }
+}
+
+fn record_input(input: Res<PlayerInput>, recorder: Option<ResMut<InputRecorder>>) {
+    if let Some(mut recorder) = recorder {
+        if recorder.recording {
+            recorder.replay.inputs.push(*input);
+        }
+    }
+}
+
+fn save_recording(world: &mut World) {
+    match world.get_resource::<InputRecorder>() {
+        Some(recorder) if recorder.recording => {}
+        _ => return,
+    }
+
+    let hash = state_hash(world);
+    let mut recorder = world.resource_mut::<InputRecorder>();
+    recorder.recording = false;
+    recorder.replay.hash = Some(hash);
+
+    match recorder.replay.save(&recorder.path) {
+        Ok(()) => info!("Replay saved to {}", recorder.path.display()),
+        Err(e) => error!("Failed to save the replay: {}", e),
+    }
+}
+
+fn save_recording_on_app_exit(world: &mut World) {
+    if world
+        .resource::<Events<AppExit>>()
+        .iter_current_update_events()
+        .next()
+        .is_some()
+    {
+        save_recording(world);
+    }
+}
+
+// The input of the replayed game, used instead of the keyboard and mouse
+struct ReplayInput {
+    inputs: Vec<PlayerInput>,
+    next: usize,
+}
+
+fn feed_replay_input(replay: Option<ResMut<ReplayInput>>, mut input: ResMut<PlayerInput>) {
+    if let Some(mut replay) = replay {
+        *input = replay.inputs.get(replay.next).copied().unwrap_or_default();
+        replay.next += 1;
+    }
+}
+
+// Play the replay in the headless app, and return the hash of the resulting game state
+pub fn run_replay(replay: &Replay) -> u64 {
+    let mut app = headless_app();
+    app.world.resource_mut::<GameRng>().next_seed = Some(replay.seed);
+    app.insert_resource(ReplayInput {
+        inputs: replay.inputs.clone(),
+        next: 0,
+    });
+
+    // the game starts during the first frames, then there is exactly one tick per frame
+    let max_frames = replay.inputs.len() + 10;
+    let mut frames = 0;
+    while app.world.resource::<ReplayInput>().next < replay.inputs.len() {
+        assert!(frames < max_frames, "the replay did not run its ticks");
+        app.update();
+        frames += 1;
+    }
+
+    state_hash(&mut app.world)
+}
+
+pub fn verify_replay(replay: &Replay) -> Result<(), ReplayError> {
+    let expected = replay.hash.ok_or(ReplayError::MissingHash)?;
+    let found = run_replay(replay);
+
+    if found == expected {
+        Ok(())
+    } else {
+        Err(ReplayError::Mismatch { expected, found })
+    }
+}
+
+// FNV-1a over the values written as little-endian bytes. Unlike the std hasher and the std
+// 'Hash' impls, the result is the same with every Rust version and on every platform
+struct StateHasher(u64);
+
+impl StateHasher {
+    fn new() -> Self {
+        StateHasher(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.write(&[value]);
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    fn write_i16(&mut self, value: i16) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    // the counts and the lengths as 64 bits, whatever the size of 'usize'
+    fn write_count(&mut self, count: usize) {
+        self.write_u64(count as u64);
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_count(value.len());
+        self.write(value.as_bytes());
+    }
+
+    fn write_duration(&mut self, value: Duration) {
+        self.write_u64(value.as_secs());
+        self.write_u32(value.subsec_nanos());
+    }
+
+    fn write_vec3(&mut self, v: Vec3) {
+        self.write_u32(v.x.to_bits());
+        self.write_u32(v.y.to_bits());
+        self.write_u32(v.z.to_bits());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Hash everything the simulation changes: the player position, the resources, the trees...
+pub fn state_hash(world: &mut World) -> u64 {
+    let mut hasher = StateHasher::new();
+
+    let mut player_query =
+        world.query_filtered::<(&Transform, Option<&Interpolated>), With<Player>>();
+    for (transform, interpolated) in player_query.iter(world) {
+        let translation = interpolated.map_or(transform.translation, |i| i.current());
+        hasher.write_vec3(translation);
+    }
+    let mut velocity_query = world.query_filtered::<&Velocity, With<Player>>();
+    for velocity in velocity_query.iter(world) {
+        hasher.write_u32(velocity.0.x.to_bits());
+        hasher.write_u32(velocity.0.y.to_bits());
+    }
+
+    let mut stamina_query = world.query_filtered::<&Stamina, With<Player>>();
+    for stamina in stamina_query.iter(world) {
+        hasher.write_u32(stamina.current().to_bits());
+        hasher.write_bool(stamina.is_exhausted());
+    }
+
+    let mut wood_query = world.query_filtered::<&ResourceCounter, With<WoodResource>>();
+    for counter in wood_query.iter(world) {
+        hasher.write_u32(counter.0);
+    }
+    let mut coin_query = world.query_filtered::<&ResourceCounter, With<CoinResource>>();
+    for counter in coin_query.iter(world) {
+        hasher.write_u32(counter.0);
+    }
+    let mut charcoal_query = world.query_filtered::<&ResourceCounter, With<CharcoalResource>>();
+    for counter in charcoal_query.iter(world) {
+        hasher.write_u32(counter.0);
+    }
+    let mut sapling_query = world.query_filtered::<&ResourceCounter, With<SaplingResource>>();
+    for counter in sapling_query.iter(world) {
+        hasher.write_u32(counter.0);
+    }
+
+    // the trees order in the query depends on the entities, not on the game
+    let mut tree_query = world.query::<(&Tree, &Transform, Option<&Sapling>, Option<&Burning>)>();
+    let mut trees: Vec<(u32, u32, i16, u32, bool)> = tree_query
+        .iter(world)
+        .map(|(tree, transform, sapling, burning)| {
+            (
+                transform.translation.x.to_bits(),
+                transform.translation.y.to_bits(),
+                tree.health,
+                sapling.map_or(1.0, |sapling| sapling.growth).to_bits(),
+                burning.is_some(),
+            )
+        })
+        .collect();
+    trees.sort_unstable();
+    hasher.write_count(trees.len());
+    for (x, y, health, growth, burning) in trees {
+        hasher.write_u32(x);
+        hasher.write_u32(y);
+        hasher.write_i16(health);
+        hasher.write_u32(growth);
+        hasher.write_bool(burning);
+    }
+
+    let mut worker_query = world.query::<(&Worker, &Transform, Option<&Interpolated>)>();
+    let mut workers: Vec<(u32, u32, WorkerState, u32)> = worker_query
+        .iter(world)
+        .map(|(worker, transform, interpolated)| {
+            let translation = interpolated.map_or(transform.translation, |i| i.current());
+            (
+                translation.x.to_bits(),
+                translation.y.to_bits(),
+                worker.state(),
+                worker.wood(),
+            )
+        })
+        .collect();
+    workers.sort_unstable();
+    hasher.write_count(workers.len());
+    for (x, y, state, wood) in workers {
+        hasher.write_u32(x);
+        hasher.write_u32(y);
+        hasher.write_u8(state as u8);
+        hasher.write_u32(wood);
+    }
+
+    let mut animal_query = world.query::<(&Animal, &Transform, Option<&Interpolated>)>();
+    let mut animals: Vec<(u32, u32, AnimalKind, AnimalState)> = animal_query
+        .iter(world)
+        .map(|(animal, transform, interpolated)| {
+            let translation = interpolated.map_or(transform.translation, |i| i.current());
+            (
+                translation.x.to_bits(),
+                translation.y.to_bits(),
+                animal.kind(),
+                animal.state(),
+            )
+        })
+        .collect();
+    animals.sort_unstable();
+    hasher.write_count(animals.len());
+    for (x, y, kind, state) in animals {
+        hasher.write_u32(x);
+        hasher.write_u32(y);
+        hasher.write_u8(kind as u8);
+        hasher.write_u8(state as u8);
+    }
+
+    let mut post_query = world.query::<(&TradingPost, &Transform)>();
+    let mut posts: Vec<(u32, u32, Vec<u32>)> = post_query
+        .iter(world)
+        .map(|(post, transform)| {
+            (
+                transform.translation.x.to_bits(),
+                transform.translation.y.to_bits(),
+                post.offers.iter().map(|offer| offer.stock).collect(),
+            )
+        })
+        .collect();
+    posts.sort_unstable();
+    hasher.write_count(posts.len());
+    for (x, y, stocks) in posts {
+        hasher.write_u32(x);
+        hasher.write_u32(y);
+        hasher.write_count(stocks.len());
+        for stock in stocks {
+            hasher.write_u32(stock);
+        }
+    }
+
+    // there is only one merchant
+    let mut merchant_query = world.query::<&Merchant>();
+    for merchant in merchant_query.iter(world) {
+        hasher.write_count(merchant.deals.len());
+        for deal in merchant.deals.iter() {
+            hasher.write_u32(deal.stock());
+        }
+    }
+    hasher.write_bool(world.resource::<OpenTrade>().0.is_some());
+
+    for flag in world.resource::<DialogueFlags>().iter() {
+        hasher.write_str(flag);
+    }
+    if let Some(dialogue) = &world.resource::<OpenDialogue>().0 {
+        hasher.write_str(dialogue.conversation());
+        hasher.write_count(dialogue.node());
+        hasher.write_count(dialogue.typed());
+    }
+    for quest in world.resource::<QuestLog>().iter() {
+        hasher.write_str(quest.name());
+        hasher.write_u8(quest.status() as u8);
+        hasher.write_bool(quest.deadline().is_some());
+        if let Some(deadline) = quest.deadline() {
+            hasher.write_duration(deadline);
+        }
+        for (_, done) in quest.objectives() {
+            hasher.write_u32(done);
+        }
+    }
+
+    let mut log_query =
+        world.query_filtered::<&Transform, Or<(With<FallenLog>, With<CharredStump>)>>();
+    let mut logs: Vec<(u32, u32)> = log_query
+        .iter(world)
+        .map(|transform| {
+            (
+                transform.translation.x.to_bits(),
+                transform.translation.y.to_bits(),
+            )
+        })
+        .collect();
+    logs.sort_unstable();
+    hasher.write_count(logs.len());
+    for (x, y) in logs {
+        hasher.write_u32(x);
+        hasher.write_u32(y);
+    }
+    hasher.write_u8(world.resource::<Weather>().state as u8);
+
+    let clock = world.resource::<GameClock>();
+    hasher.write_u32(clock.day());
+    hasher.write_duration(clock.time_of_day());
+
+    let stats = world.resource::<GameStats>();
+    hasher.write_u32(stats.wood_chopped);
+    hasher.write_u32(stats.trees_felled);
+    hasher.write_u32(stats.coins_earned);
+
+    hasher.finish()
+}
@@ -1,6 +1,10 @@
 use crate::{
+    depth::HUD_Z,
+    game_state::{AppState, InGame},
+    loading::GameAssets,
     map::{TILE_COUNT_X, TILE_COUNT_Y, TILE_SIZE},
-    player::{player_movement, Player},
+    player::Player,
+    tick::interpolate_translation,
     SCALE,
 };
 use bevy::prelude::*;
@@ -9,16 +13,17 @@ pub struct ResourceCounterPlugin;
 
 impl Plugin for ResourceCounterPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_resources)
-            .add_system(update_resource_pos.after(player_movement))
-            .add_system(update_res_count);
+        app.add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_resources))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(update_resource_pos.after(interpolate_translation))
+                    .with_system(update_res_count),
+            );
     }
 }
 
-type SpritePath = String;
-
-// Struct to pass to create a new resource counter (Component, "assets/sprite.png".to_string())
-pub struct ResourceToCount<T: Component + Clone>(T, SpritePath);
+// Struct to pass to create a new resource counter (Components, sprite handle)
+pub struct ResourceToCount<T: Bundle>(T, Handle<Image>);
 
 // Represent the Resource sprite + the text (the actual 'counter')
 #[derive(Component)]
@@ -33,36 +38,104 @@ pub struct ResourceCounter(pub u32);
 pub struct CoinResource;
 #[derive(Component, Clone)]
 pub struct WoodResource;
+#[derive(Component, Clone)]
+pub struct CharcoalResource;
+#[derive(Component, Clone)]
+pub struct SaplingResource;
+
+// The items the player carries and can sell, each one has its own counter
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Item {
+    Wood,
+    Charcoal,
+    Sapling,
+}
+
+impl Item {
+    // price of a single item, before the multiplier of the trading post
+    pub fn base_price(self) -> u32 {
+        match self {
+            Item::Wood => 3,
+            Item::Charcoal => 5,
+            Item::Sapling => 8,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Item::Wood => "wood",
+            Item::Charcoal => "charcoal",
+            Item::Sapling => "sapling",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Item::Wood, Item::Charcoal, Item::Sapling]
+            .into_iter()
+            .find(|item| item.name() == name)
+    }
+
+    pub fn icon(self, game_assets: &GameAssets) -> Handle<Image> {
+        match self {
+            Item::Wood => game_assets.wood_log.clone(),
+            Item::Charcoal => game_assets.charcoal.clone(),
+            Item::Sapling => game_assets.sapling.clone(),
+        }
+    }
+}
 
 // spawn all the wanted resources to be counted
-fn setup_resources(mut commands: Commands, windows: Res<Windows>, asset_server: Res<AssetServer>) {
+fn setup_resources(mut commands: Commands, windows: Res<Windows>, game_assets: Res<GameAssets>) {
     new_resource_counter(
         &mut commands,
         &windows,
-        &asset_server,
-        ResourceToCount(CoinResource, String::from("coin.png")),
+        &game_assets,
+        ResourceToCount((CoinResource,), game_assets.coin.clone()),
         0.0,
     );
     new_resource_counter(
         &mut commands,
         &windows,
-        &asset_server,
-        ResourceToCount(WoodResource, String::from("wood_log.png")),
+        &game_assets,
+        ResourceToCount((WoodResource, Item::Wood), game_assets.wood_log.clone()),
         45.0,
     );
+    new_resource_counter(
+        &mut commands,
+        &windows,
+        &game_assets,
+        ResourceToCount(
+            (CharcoalResource, Item::Charcoal),
+            game_assets.charcoal.clone(),
+        ),
+        90.0,
+    );
+    new_resource_counter(
+        &mut commands,
+        &windows,
+        &game_assets,
+        ResourceToCount(
+            (SaplingResource, Item::Sapling),
+            game_assets.sapling.clone(),
+        ),
+        135.0,
+    );
 }
 
-fn new_resource_counter<T: Component + Clone>(
+fn new_resource_counter<T: Bundle>(
     commands: &mut Commands,
     windows: &Res<Windows>,
-    asset_server: &Res<AssetServer>,
+    game_assets: &Res<GameAssets>,
     resource: ResourceToCount<T>,
     pos_y_offset: f32,
 ) {
-    let window = windows.primary();
+    // no window in the headless app, the counters are only there to keep track of the count
+    let (width, height) = windows
+        .get_primary()
+        .map_or((0.0, 0.0), |window| (window.width(), window.height()));
 
-    let pos_y = window.height() / 2.15 - pos_y_offset;
-    let pos_x = window.width() / 2.1;
+    let pos_y = height / 2.15 - pos_y_offset;
+    let pos_x = width / 2.1;
 
     let resource_sprite = commands
         .spawn_bundle(SpriteBundle {
@@ -70,8 +143,9 @@ fn new_resource_counter<T: Component + Clone>(
                 color: Color::rgba(1.0, 1.0, 1.0, 0.9),
                 ..Default::default()
             },
-            texture: asset_server.load(&resource.1),
-            transform: Transform::from_xyz(pos_x, pos_y, 50.0).with_scale(Vec3::splat(SCALE * 0.5)),
+            texture: resource.1,
+            transform: Transform::from_xyz(pos_x, pos_y, HUD_Z)
+                .with_scale(Vec3::splat(SCALE * 0.5)),
             ..Default::default()
         })
         .id();
@@ -80,7 +154,7 @@ fn new_resource_counter<T: Component + Clone>(
             text: Text::with_section(
                 "0",
                 TextStyle {
-                    font: asset_server.load("fonts/Fixedsys Excelsior 3.01 Regular.ttf"),
+                    font: game_assets.font.clone(),
                     font_size: 15.0,
                     color: Color::WHITE,
                 },
@@ -89,17 +163,18 @@ fn new_resource_counter<T: Component + Clone>(
                     ..Default::default()
                 },
             ),
-            transform: Transform::from_xyz(-25.0, 9.0, 50.0),
+            transform: Transform::from_xyz(-25.0, 9.0, 1.0),
             ..Default::default()
         })
         .insert(ResourceCounter(0))
-        .insert(resource.0.clone())
+        .insert_bundle(resource.0)
         .id();
 
     commands
         .entity(resource_sprite)
         .add_child(resource_text)
-        .insert(GameResource);
+        .insert(GameResource)
+        .insert(InGame);
 }
 
 fn update_resource_pos(
@@ -109,7 +184,10 @@ fn update_resource_pos(
 ) {
     let mut pos_y_offset = 0.0;
 
-    let window = windows.primary();
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
     let pos_x = window.width() / 2.1;
 
     let player_transform = player_query.single();
@@ -0,0 +1,265 @@
+use crate::{
+    clock::{reset_game_clock, GameClock},
+    dialogue::{reset_dialogues, DialogueFlags},
+    game_state::AppState,
+    quests::{reset_quests, QuestCatalog, QuestLog, QuestStatus},
+};
+use bevy::{app::AppExit, prelude::*};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Last, save_game_on_app_exit)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(
+                    load_game
+                        .after(reset_game_clock)
+                        .after(reset_dialogues)
+                        .after(reset_quests),
+                ),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(save_game));
+    }
+}
+
+// What is kept from one game to the next: the flags set by the dialogues and the quests taken
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaveGame {
+    pub flags: Vec<String>,
+    pub quests: Vec<SavedQuest>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedQuest {
+    pub name: String,
+    pub status: QuestStatus,
+    // the time left before the deadline, the clock starts again with each game
+    pub time_left: Option<Duration>,
+    pub progress: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Parse(line) => write!(f, "invalid save line: '{}'", line),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+fn status_name(status: QuestStatus) -> &'static str {
+    match status {
+        QuestStatus::Active => "active",
+        QuestStatus::Completed => "completed",
+        QuestStatus::Failed => "failed",
+    }
+}
+
+fn status_from_name(name: &str) -> Option<QuestStatus> {
+    match name {
+        "active" => Some(QuestStatus::Active),
+        "completed" => Some(QuestStatus::Completed),
+        "failed" => Some(QuestStatus::Failed),
+        _ => None,
+    }
+}
+
+// The save file is a text file:
+//   flag <flag>
+//   quest <name> <active|completed|failed> <milliseconds left, or - without deadline> <progress>...
+//   (one progress per objective of the quest, in the order of the catalog)
+impl fmt::Display for SaveGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for flag in self.flags.iter() {
+            writeln!(f, "flag {}", flag)?;
+        }
+        for quest in self.quests.iter() {
+            write!(f, "quest {} {}", quest.name, status_name(quest.status))?;
+            match quest.time_left {
+                Some(time_left) => write!(f, " {}", time_left.as_millis())?,
+                None => write!(f, " -")?,
+            }
+            for progress in quest.progress.iter() {
+                write!(f, " {}", progress)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl SaveGame {
+    pub fn new(clock: &GameClock, flags: &DialogueFlags, quest_log: &QuestLog) -> Self {
+        let now = clock.elapsed();
+        SaveGame {
+            flags: flags.iter().cloned().collect(),
+            quests: quest_log
+                .iter()
+                .map(|quest| SavedQuest {
+                    name: quest.name().to_string(),
+                    status: quest.status(),
+                    // in whole milliseconds, as in the file
+                    time_left: quest.deadline().map(|deadline| {
+                        Duration::from_millis(deadline.saturating_sub(now).as_millis() as u64)
+                    }),
+                    progress: quest.objectives().map(|(_, done)| done).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let mut save = SaveGame::default();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let parse_error = || SaveError::Parse(line.to_string());
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            match words[..] {
+                ["flag", flag] => save.flags.push(flag.to_string()),
+                ["quest", name, status, time_left, ref progress @ ..] => {
+                    let time_left = match time_left {
+                        "-" => None,
+                        millis => Some(Duration::from_millis(
+                            millis.parse().map_err(|_| parse_error())?,
+                        )),
+                    };
+                    save.quests.push(SavedQuest {
+                        name: name.to_string(),
+                        status: status_from_name(status).ok_or_else(parse_error)?,
+                        time_left,
+                        progress: progress
+                            .iter()
+                            .map(|done| done.parse().map_err(|_| parse_error()))
+                            .collect::<Result<_, _>>()?,
+                    });
+                }
+                _ => return Err(parse_error()),
+            }
+        }
+        Ok(save)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        SaveGame::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+
+    // the quests which are not in the catalog anymore, or whose objectives changed, are dropped
+    pub fn restore(
+        &self,
+        clock: &GameClock,
+        catalog: &QuestCatalog,
+        flags: &mut DialogueFlags,
+        quest_log: &mut QuestLog,
+    ) {
+        for flag in self.flags.iter() {
+            flags.set(flag);
+        }
+
+        let now = clock.elapsed();
+        for quest in self.quests.iter() {
+            let data = match catalog.get(&quest.name) {
+                Some(data) if data.objectives.len() == quest.progress.len() => data,
+                _ => {
+                    warn!("Quest dropped from the save file: {}", quest.name);
+                    continue;
+                }
+            };
+            quest_log.restore(
+                data.clone(),
+                quest.progress.clone(),
+                quest.time_left.map(|time_left| now + time_left),
+                quest.status,
+            );
+        }
+    }
+}
+
+// Where the game is saved, the save is loaded when a game starts and written when it ends
+// or the app is closed
+pub struct SaveFile {
+    path: PathBuf,
+    playing: bool,
+}
+
+impl SaveFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SaveFile {
+            path: path.into(),
+            playing: false,
+        }
+    }
+}
+
+fn load_game(
+    clock: Res<GameClock>,
+    catalog: Res<QuestCatalog>,
+    save_file: Option<ResMut<SaveFile>>,
+    mut flags: ResMut<DialogueFlags>,
+    mut quest_log: ResMut<QuestLog>,
+) {
+    let mut save_file = match save_file {
+        Some(save_file) => save_file,
+        None => return,
+    };
+    save_file.playing = true;
+
+    match SaveGame::load(&save_file.path) {
+        Ok(save) => save.restore(&clock, &catalog, &mut flags, &mut quest_log),
+        // the first game, there is nothing to load
+        Err(SaveError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => error!("Failed to load the save file: {}", e),
+    }
+}
+
+fn save_game(
+    clock: Res<GameClock>,
+    flags: Res<DialogueFlags>,
+    quest_log: Res<QuestLog>,
+    save_file: Option<ResMut<SaveFile>>,
+) {
+    let mut save_file = match save_file {
+        Some(save_file) if save_file.playing => save_file,
+        _ => return,
+    };
+    save_file.playing = false;
+
+    match SaveGame::new(&clock, &flags, &quest_log).save(&save_file.path) {
+        Ok(()) => info!("Game saved to {}", save_file.path.display()),
+        Err(e) => error!("Failed to save the game: {}", e),
+    }
+}
+
+fn save_game_on_app_exit(
+    exit_events: EventReader<AppExit>,
+    clock: Res<GameClock>,
+    flags: Res<DialogueFlags>,
+    quest_log: Res<QuestLog>,
+    save_file: Option<ResMut<SaveFile>>,
+) {
+    if !exit_events.is_empty() {
+        save_game(clock, flags, quest_log, save_file);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::resource_counter::Item;
+
+// how many in-game days a season lasts
+pub const DAYS_PER_SEASON: u32 = 3;
+
+// The seasons follow each other from the first day of the game, starting with spring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    // the season of a day of the game, the first day is 1
+    pub fn from_day(day: u32) -> Self {
+        match (day.saturating_sub(1) / DAYS_PER_SEASON) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    // the tree animation frames in the sprite sheet: the leaves change color, winter trees are bare
+    pub fn tree_frames(self) -> Vec<usize> {
+        let first = match self {
+            Season::Spring => 20,
+            Season::Summer => 15,
+            Season::Autumn => 25,
+            Season::Winter => 30,
+        };
+        (first..first + 5).collect()
+    }
+
+    // how fast the saplings grow, 1.0 is the normal speed
+    pub fn growth_rate(self) -> f32 {
+        match self {
+            Season::Spring => 1.5,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.5,
+            Season::Winter => 0.0,
+        }
+    }
+
+    // how often a new tree grows when there are missing ones, none in winter
+    pub fn tree_respawn_seconds(self) -> Option<f32> {
+        match self {
+            Season::Spring => Some(20.0),
+            Season::Summer => Some(30.0),
+            Season::Autumn => Some(45.0),
+            Season::Winter => None,
+        }
+    }
+
+    // multiplier on the price of the items, fuel is worth more when it gets cold and the
+    // saplings always cost the same
+    pub fn price_multiplier(self, item: Item) -> f32 {
+        match (self, item) {
+            (Season::Spring, Item::Wood | Item::Charcoal) => 0.9,
+            (Season::Summer, Item::Wood | Item::Charcoal) => 1.0,
+            (Season::Autumn, Item::Wood | Item::Charcoal) => 1.25,
+            (Season::Winter, Item::Wood | Item::Charcoal) => 1.5,
+            (_, Item::Sapling) => 1.0,
+        }
+    }
+
+    // how easily the trees catch fire, 1.0 is the driest
+    pub fn dryness(self) -> f32 {
+        match self {
+            Season::Spring => 0.6,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.8,
+            Season::Winter => 0.3,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    game_state::{AppState, InGame},
+    loading::GameAssets,
+    player::{chop_wood_action, Player, PlayerState},
+    tick::{FixedTick, FixedUpdateStage},
+};
+use bevy::prelude::*;
+
+pub struct StaminaPlugin;
+
+impl Plugin for StaminaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(FixedUpdateStage, regenerate_stamina.after(chop_wood_action))
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_stamina_bar))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(update_stamina_bar),
+            );
+    }
+}
+
+// stamina regenerated per second while standing
+const STAMINA_REGEN: f32 = 15.0;
+// once exhausted, the stamina has to be back to this part of the max to be used again
+const EXHAUSTION_RECOVERY: f32 = 0.3;
+
+// Consumed by the player actions, when it runs out the player is exhausted:
+// no more sprinting and slower swings until it regenerates
+#[derive(Component)]
+pub struct Stamina {
+    current: f32,
+    max: f32,
+    exhausted: bool,
+}
+
+impl Stamina {
+    pub fn new(max: f32) -> Self {
+        Stamina {
+            current: max,
+            max,
+            exhausted: false,
+        }
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    pub fn consume(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+        if self.current == 0.0 {
+            self.exhausted = true;
+        }
+    }
+
+    pub fn regenerate(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+        if self.current >= self.max * EXHAUSTION_RECOVERY {
+            self.exhausted = false;
+        }
+    }
+}
+
+// Move faster while the sprint key is held, for a stamina cost per second
+#[derive(Component)]
+pub struct Sprint {
+    pub speed_multiplier: f32,
+    pub stamina_cost: f32,
+}
+
+fn regenerate_stamina(
+    tick: Res<FixedTick>,
+    mut query: Query<(&mut Stamina, &PlayerState), With<Player>>,
+) {
+    for (mut stamina, player_state) in query.iter_mut() {
+        if let PlayerState::Stand(_) = player_state {
+            stamina.regenerate(STAMINA_REGEN * tick.delta_seconds());
+        }
+    }
+}
+
+// HUD bar showing the stamina of the player
+#[derive(Component)]
+struct StaminaBar;
+
+const STAMINA_COLOR: Color = Color::rgb(0.35, 0.75, 0.3);
+const EXHAUSTED_COLOR: Color = Color::rgb(0.75, 0.3, 0.25);
+
+fn spawn_stamina_bar(mut commands: Commands, game_assets: Res<GameAssets>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(15.0),
+                    top: Val::Px(15.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(160.0), Val::Px(14.0)),
+                padding: Rect::all(Val::Px(2.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            ..Default::default()
+        })
+        .insert(InGame)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..Default::default()
+                    },
+                    color: STAMINA_COLOR.into(),
+                    ..Default::default()
+                })
+                .insert(StaminaBar);
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(165.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "Stamina",
+                    TextStyle {
+                        font: game_assets.font.clone(),
+                        font_size: 12.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+fn update_stamina_bar(
+    stamina_query: Query<&Stamina, With<Player>>,
+    mut bar_query: Query<(&mut Style, &mut UiColor), With<StaminaBar>>,
+) {
+    let stamina = match stamina_query.get_single() {
+        Ok(stamina) => stamina,
+        Err(_) => return,
+    };
+
+    for (mut style, mut color) in bar_query.iter_mut() {
+        style.size.width = Val::Percent(100.0 * stamina.current() / stamina.max());
+        *color = if stamina.is_exhausted() {
+            EXHAUSTED_COLOR.into()
+        } else {
+            STAMINA_COLOR.into()
+        };
+    }
+}
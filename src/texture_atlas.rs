@@ -1,10 +1,11 @@
+use crate::{game_state::AppState, loading::GameAssets};
 use bevy::prelude::*;
 
 pub struct AtlasPlugin;
 
 impl Plugin for AtlasPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system_to_stage(StartupStage::PreStartup, setup);
+        app.add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup));
     }
 }
 
@@ -13,11 +14,11 @@ pub struct AtlasHandle(pub Handle<TextureAtlas>);
 
 fn setup(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
-    let texture_handle = asset_server.load("sprite_sheet.png");
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::splat(32.0), 5, 4);
+    let texture_atlas =
+        TextureAtlas::from_grid(game_assets.sprite_sheet.clone(), Vec2::splat(32.0), 5, 12);
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
     commands.insert_resource(AtlasHandle(texture_atlas_handle));
 }
@@ -0,0 +1,139 @@
+use crate::game_state::AppState;
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use std::time::Duration;
+
+pub struct TickPlugin;
+
+impl Plugin for TickPlugin {
+    fn build(&self, app: &mut App) {
+        // keep the tick rate if it was configured before adding the plugin
+        if !app.world.contains_resource::<FixedTick>() {
+            app.insert_resource(FixedTick::from_hz(60.0));
+        }
+
+        app.add_stage_before(
+            CoreStage::Update,
+            FixedUpdateStage,
+            SystemStage::parallel().with_run_criteria(run_fixed_tick),
+        )
+        .add_system_to_stage(CoreStage::PreUpdate, accumulate_tick)
+        .add_system_to_stage(CoreStage::PreUpdate, restore_translation)
+        .add_system_to_stage(
+            FixedUpdateStage,
+            store_previous_translation.exclusive_system().at_start(),
+        )
+        .add_system_to_stage(CoreStage::Update, interpolate_translation);
+    }
+}
+
+// The stage running the gameplay logic, zero, one or several times per frame
+// so it always runs at the same rate (only while playing)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct FixedUpdateStage;
+
+// The fixed rate of the simulation, gameplay systems use the tick delta instead of 'Time'
+pub struct FixedTick {
+    step: Duration,
+    accumulator: Duration,
+    // run exactly one tick per frame whatever the real time elapsed (used by the headless app)
+    pub lockstep: bool,
+}
+
+// never try to catch up more than this amount of ticks in a single frame
+const MAX_TICKS_PER_FRAME: u32 = 5;
+
+impl FixedTick {
+    pub fn from_hz(hz: f64) -> Self {
+        FixedTick {
+            step: Duration::from_secs_f64(1.0 / hz),
+            accumulator: Duration::ZERO,
+            lockstep: false,
+        }
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.step
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    // how far the real time is between the last tick and the next one, from 0.0 to 1.0
+    // (in lockstep every frame ends right on a tick)
+    pub fn alpha(&self) -> f32 {
+        if self.lockstep {
+            return 1.0;
+        }
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+// Entities moved by the simulation: the transform is only updated on ticks, in between the
+// rendered translation is interpolated between the translation of the last two ticks
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl Interpolated {
+    pub fn new(translation: Vec3) -> Self {
+        Interpolated {
+            previous: translation,
+            current: translation,
+        }
+    }
+
+    // the translation of the last tick (not the rendered one)
+    pub fn current(&self) -> Vec3 {
+        self.current
+    }
+}
+
+fn accumulate_tick(time: Res<Time>, state: Res<State<AppState>>, mut tick: ResMut<FixedTick>) {
+    if *state.current() != AppState::Playing {
+        tick.accumulator = Duration::ZERO;
+        return;
+    }
+
+    if tick.lockstep {
+        tick.accumulator = tick.step;
+    } else {
+        tick.accumulator = (tick.accumulator + time.delta()).min(tick.step * MAX_TICKS_PER_FRAME);
+    }
+}
+
+fn run_fixed_tick(state: Res<State<AppState>>, mut tick: ResMut<FixedTick>) -> ShouldRun {
+    if *state.current() == AppState::Playing && tick.accumulator >= tick.step {
+        let step = tick.step;
+        tick.accumulator -= step;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+// put back the translation of the last tick before running the simulation
+fn restore_translation(mut query: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.current;
+    }
+}
+
+fn store_previous_translation(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = transform.translation;
+    }
+}
+
+pub fn interpolate_translation(
+    tick: Res<FixedTick>,
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    let alpha = tick.alpha();
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        interpolated.current = transform.translation;
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    clock::{advance_clock, Dawn, GameClock, Shaded},
+    depth::YSorted,
+    game_state::{AppState, GameStats, InGame},
+    interaction::{update_interactions, Interactable, Interacted, InteractionKind},
+    loading::GameAssets,
+    map::{tile_to_world, MapLayout, TILE_SIZE},
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{CoinResource, Item, ResourceCounter},
+    season::Season,
+    tick::FixedUpdateStage,
+    SCALE,
+};
+use bevy::prelude::*;
+
+pub struct TradingPostPlugin;
+
+impl Plugin for TradingPostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(FixedUpdateStage, sell_items.after(update_interactions))
+            .add_system_to_stage(
+                FixedUpdateStage,
+                restock_trading_posts.after(advance_clock).after(sell_items),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(spawn_trading_posts),
+            );
+    }
+}
+
+// A place where the player sells items, each post only buys some items
+// and buys its whole stock again every morning
+#[derive(Component)]
+pub struct TradingPost {
+    pub offers: Vec<TradeOffer>,
+}
+
+// An item bought by a trading post, the stock is the amount of items it still buys
+#[derive(Clone)]
+pub struct TradeOffer {
+    pub item: Item,
+    pub price_multiplier: f32,
+    pub stock: u32,
+    pub max_stock: u32,
+}
+
+impl TradeOffer {
+    pub fn new(item: Item, price_multiplier: f32, max_stock: u32) -> Self {
+        TradeOffer {
+            item,
+            price_multiplier,
+            stock: max_stock,
+            max_stock,
+        }
+    }
+
+    pub fn price(&self, season: Season) -> u32 {
+        let multiplier = self.price_multiplier * season.price_multiplier(self.item);
+        (self.item.base_price() as f32 * multiplier).round() as u32
+    }
+}
+
+fn spawn_trading_posts(
+    game_assets: Res<GameAssets>,
+    map_layout: Res<MapLayout>,
+    mut commands: Commands,
+) {
+    for post in map_layout.trading_posts.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: game_assets.sell_sign.clone(),
+                transform: Transform::from_scale(Vec3::splat(SCALE * 0.5))
+                    .with_translation(tile_to_world(post.tile).extend(0.0)),
+                ..Default::default()
+            })
+            .insert(TradingPost {
+                offers: post.offers.clone(),
+            })
+            .insert(Shaded)
+            .insert(YSorted { anchor: -10.0 })
+            .insert(Interactable {
+                radius: TILE_SIZE * SCALE * 0.6,
+                prompt: game_assets.e_key.clone(),
+                kind: InteractionKind::Sell,
+            })
+            .insert(InGame);
+    }
+}
+
+// sell everything the trading post buys, as long as it has stock left
+fn sell_items(
+    clock: Res<GameClock>,
+    game_assets: Res<GameAssets>,
+    mut interacted_events: EventReader<Interacted>,
+    mut stats: ResMut<GameStats>,
+    mut progress_events: EventWriter<QuestProgress>,
+    mut commands: Commands,
+    mut post_query: Query<(&mut TradingPost, &Transform)>,
+    mut coins_res_query: Query<&mut ResourceCounter, (With<CoinResource>, Without<Item>)>,
+    mut item_res_query: Query<(&mut ResourceCounter, &Item), Without<CoinResource>>,
+) {
+    for event in interacted_events.iter() {
+        if event.kind != InteractionKind::Sell {
+            continue;
+        }
+        let (mut post, post_transform) = match post_query.get_mut(event.entity) {
+            Ok(post) => post,
+            Err(_) => continue,
+        };
+        let mut coins_count = coins_res_query.single_mut();
+        let mut total_earned = 0;
+        let mut sold_out = false;
+
+        for (mut item_count, item) in item_res_query.iter_mut() {
+            if let Some(offer) = post.offers.iter_mut().find(|offer| offer.item == *item) {
+                let sold = item_count.0.min(offer.stock);
+                let earned = sold * offer.price(clock.season());
+                sold_out |= item_count.0 > 0 && offer.stock == 0;
+
+                offer.stock -= sold;
+                item_count.0 -= sold;
+                coins_count.0 += earned;
+                stats.coins_earned += earned;
+                total_earned += earned;
+                if sold > 0 {
+                    progress_events.send(QuestProgress::ItemSold(*item, sold));
+                }
+            }
+        }
+
+        let popup_position = post_transform.translation + Vec3::new(0.0, TILE_SIZE * SCALE, 0.0);
+        if total_earned > 0 {
+            trigger_popup(
+                &mut commands,
+                &game_assets,
+                popup_position,
+                Popup::text(format!("+{} coins", total_earned))
+                    .with_icon(game_assets.coin.clone())
+                    .with_color(Color::rgb(1.0, 0.85, 0.3)),
+            );
+        } else if sold_out {
+            trigger_popup(
+                &mut commands,
+                &game_assets,
+                popup_position,
+                Popup::text("Sold out!")
+                    .with_color(Color::rgb(1.0, 0.4, 0.4))
+                    .with_font_size(14.0),
+            );
+        }
+    }
+}
+
+fn restock_trading_posts(
+    mut dawn_events: EventReader<Dawn>,
+    mut post_query: Query<&mut TradingPost>,
+) {
+    if dawn_events.iter().count() == 0 {
+        return;
+    }
+    for mut post in post_query.iter_mut() {
+        for offer in post.offers.iter_mut() {
+            offer.stock = offer.max_stock;
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    clock::apply_daylight,
+    depth::OVERLAY_Z,
+    fire::spread_fire,
+    game_state::{AppState, InGame},
+    map::TILE_SIZE,
+    player::chop_wood_action,
+    tick::{FixedTick, FixedUpdateStage},
+    trees::{Tree, TREE_HEALTH},
+    weather::knock_down_trees,
+    workers::update_workers,
+    SCALE,
+};
+use bevy::{ecs::entity::Entities, prelude::*};
+use std::f32::consts::FRAC_PI_2;
+
+pub struct TreeFeedbackPlugin;
+
+impl Plugin for TreeFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TreeHit>()
+            // after the choppers but before the systems despawning trees, the commands run in
+            // order so a tree burnt down on the tick it is hit is shaken before being despawned
+            .add_system_to_stage(
+                FixedUpdateStage,
+                shake_hit_trees.after(update_workers).before(spread_fire),
+            )
+            // after the shake, both rotate the trees
+            .add_system_to_stage(
+                FixedUpdateStage,
+                fell_trees.after(chop_wood_action).after(shake_hit_trees),
+            )
+            .add_system_to_stage(FixedUpdateStage, show_health_bars.after(knock_down_trees))
+            .add_system_to_stage(FixedUpdateStage, update_health_bars.after(show_health_bars))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(flash_hit_trees.after(apply_daylight)),
+            );
+    }
+}
+
+// how long the health bar stays above a damaged tree, it fades during the last second
+const HEALTH_BAR_SECONDS: f32 = 3.0;
+const HEALTH_BAR_WIDTH: f32 = TILE_SIZE * SCALE * 0.6;
+const HEALTH_BAR_HEIGHT: f32 = 6.0;
+const SHAKE_SECONDS: f32 = 0.25;
+const FLASH_SECONDS: f32 = 0.08;
+const FLASH_COLOR: Color = Color::rgb(1.0, 0.55, 0.55);
+const FALL_SECONDS: f32 = 0.6;
+// the felled tree lies on the ground a moment before disappearing
+const LIE_SECONDS: f32 = 0.8;
+
+// Sent when the player hits a tree with the axe
+pub struct TreeHit(pub Entity);
+
+#[derive(Component)]
+struct HitShake(Timer);
+
+// A felled tree, it is not a 'Tree' anymore and falls on the side opposite to the player
+#[derive(Component)]
+pub struct FallingTree {
+    timer: Timer,
+    // 1.0 to fall on the right, -1.0 on the left
+    side: f32,
+    // the translation of the tree before it started falling
+    origin: Vec3,
+}
+
+impl FallingTree {
+    pub fn new(side: f32, origin: Vec3) -> Self {
+        FallingTree {
+            timer: Timer::from_seconds(FALL_SECONDS + LIE_SECONDS, false),
+            side,
+            origin,
+        }
+    }
+}
+
+#[derive(Component)]
+struct HealthBar {
+    tree: Entity,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct HealthBarFill;
+
+fn shake_hit_trees(
+    tick: Res<FixedTick>,
+    entities: &Entities,
+    mut commands: Commands,
+    mut hit_events: EventReader<TreeHit>,
+    mut shake_query: Query<(Entity, &mut HitShake, &mut Transform, Option<&FallingTree>)>,
+) {
+    for TreeHit(tree) in hit_events.iter() {
+        // restart the shake when the tree is hit again, unless it is already gone
+        if entities.contains(*tree) {
+            commands
+                .entity(*tree)
+                .insert(HitShake(Timer::from_seconds(SHAKE_SECONDS, false)));
+        }
+    }
+
+    for (entity, mut shake, mut transform, falling) in shake_query.iter_mut() {
+        // the tree felled by the hit stops shaking, its fall rotates it instead
+        if falling.is_some() {
+            commands.entity(entity).remove::<HitShake>();
+            continue;
+        }
+
+        shake.0.tick(tick.delta());
+
+        if shake.0.finished() {
+            transform.rotation = Quat::IDENTITY;
+            commands.entity(entity).remove::<HitShake>();
+        } else {
+            let strength = 1.0 - shake.0.percent();
+            let angle = (shake.0.elapsed_secs() * 60.0).sin() * 0.08 * strength;
+            transform.rotation = Quat::from_rotation_z(angle);
+        }
+    }
+}
+
+// the flash replaces the daylight tint for a moment
+fn flash_hit_trees(mut query: Query<(&HitShake, &mut TextureAtlasSprite)>) {
+    for (shake, mut sprite) in query.iter_mut() {
+        if shake.0.elapsed_secs() < FLASH_SECONDS {
+            sprite.color = FLASH_COLOR;
+        }
+    }
+}
+
+// rotate the tree around its base, then despawn it
+fn fell_trees(
+    tick: Res<FixedTick>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut FallingTree, &mut Transform)>,
+) {
+    for (entity, mut falling, mut transform) in query.iter_mut() {
+        falling.timer.tick(tick.delta());
+        if falling.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // falls faster and faster, like a real tree
+        let progress = (falling.timer.elapsed_secs() / FALL_SECONDS).min(1.0);
+        let rotation = Quat::from_rotation_z(-falling.side * FRAC_PI_2 * progress * progress);
+        let base = falling.origin - Vec3::new(0.0, 16.0 * transform.scale.y, 0.0);
+
+        transform.rotation = rotation;
+        transform.translation = base + rotation * (falling.origin - base);
+    }
+}
+
+fn show_health_bars(
+    mut commands: Commands,
+    tree_query: Query<(Entity, &Tree, &Transform), Changed<Tree>>,
+    mut bar_query: Query<&mut HealthBar>,
+) {
+    for (tree_entity, tree, tree_transform) in tree_query.iter() {
+        if tree.health >= TREE_HEALTH {
+            continue;
+        }
+
+        if let Some(mut bar) = bar_query.iter_mut().find(|bar| bar.tree == tree_entity) {
+            bar.timer.reset();
+            continue;
+        }
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.1, 0.1, 0.1, 0.8),
+                    custom_size: Some(Vec2::new(HEALTH_BAR_WIDTH, HEALTH_BAR_HEIGHT)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(health_bar_position(tree_transform)),
+                ..Default::default()
+            })
+            .insert(HealthBar {
+                tree: tree_entity,
+                timer: Timer::from_seconds(HEALTH_BAR_SECONDS, false),
+            })
+            .insert(InGame)
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(
+                                HEALTH_BAR_WIDTH - 2.0,
+                                HEALTH_BAR_HEIGHT - 2.0,
+                            )),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                        ..Default::default()
+                    })
+                    .insert(HealthBarFill);
+            });
+    }
+}
+
+fn update_health_bars(
+    tick: Res<FixedTick>,
+    mut commands: Commands,
+    tree_query: Query<(&Tree, &Transform), Without<HealthBar>>,
+    mut bar_query: Query<
+        (
+            Entity,
+            &mut HealthBar,
+            &mut Transform,
+            &mut Sprite,
+            &Children,
+        ),
+        Without<HealthBarFill>,
+    >,
+    mut fill_query: Query<(&mut Transform, &mut Sprite), (With<HealthBarFill>, Without<Tree>)>,
+) {
+    for (bar_entity, mut bar, mut bar_transform, mut bar_sprite, children) in bar_query.iter_mut() {
+        bar.timer.tick(tick.delta());
+
+        // the tree was felled or burned
+        let (tree, tree_transform) = match tree_query.get(bar.tree) {
+            Ok(tree) if !bar.timer.finished() => tree,
+            _ => {
+                commands.entity(bar_entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        let alpha = (HEALTH_BAR_SECONDS - bar.timer.elapsed_secs()).min(1.0);
+        let health = tree.health.max(0) as f32 / TREE_HEALTH as f32;
+
+        bar_transform.translation = health_bar_position(tree_transform);
+        bar_sprite.color.set_a(0.8 * alpha);
+
+        for child in children.iter() {
+            if let Ok((mut fill_transform, mut fill_sprite)) = fill_query.get_mut(*child) {
+                let width = HEALTH_BAR_WIDTH - 2.0;
+                fill_transform.scale.x = health;
+                fill_transform.translation.x = -width * (1.0 - health) / 2.0;
+                fill_sprite.color = Color::rgba(1.0 - health, health, 0.2, alpha);
+            }
+        }
+    }
+}
+
+fn health_bar_position(tree_transform: &Transform) -> Vec3 {
+    (tree_transform.translation.truncate() + Vec2::new(0.0, TILE_SIZE * SCALE * 0.6))
+        .extend(OVERLAY_Z)
+}
@@ -1,25 +1,47 @@
 use crate::{
     animations::{Animation, AnimationTimer, Animations},
-    map::{spawn_map, Map},
+    clock::{GameClock, Shaded},
+    depth::YSorted,
+    fire::Burning,
+    game_state::{reset_game_rng, AppState, GameRng, GameStats, InGame},
+    interaction::Interactable,
+    loading::GameAssets,
+    map::{spawn_map, tile_to_world, world_to_tile, Map},
     map::{TILE_COUNT_X, TILE_COUNT_Y, TILE_SIZE},
-    player::Player,
+    player::{chop_wood_action, player_feet, Direction, Player, PlayerInput},
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{ResourceCounter, SaplingResource},
+    season::Season,
     texture_atlas::AtlasHandle,
+    tick::{FixedTick, FixedUpdateStage},
+    tree_feedback::{FallingTree, TreeHit},
+    weather::Weather,
     SCALE,
 };
 use bevy::prelude::*;
-use rand::{self, Rng};
+use rand::Rng;
+use std::time::Duration;
 
 // TREE_SIZE: Vec2 = Vec2::new(23.0, 32.0);
 
 pub const TREE_AMOUNT: usize = 20;
+pub const TREE_HEALTH: i16 = 100;
+// where the tree touches the ground, from the center of its sprite
+pub const TREE_ROOT_Y: f32 = -11.0;
 
 pub struct TreePlugin;
 
 impl Plugin for TreePlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_trees.after(spawn_map))
-            .add_system(check_tree_amount)
-            .add_system(animate_tree);
+        app.add_system_to_stage(FixedUpdateStage, check_tree_amount.after(chop_wood_action))
+            .add_system_to_stage(FixedUpdateStage, grow_saplings.after(check_tree_amount))
+            .add_system_to_stage(FixedUpdateStage, plant_sapling.after(chop_wood_action))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_trees.after(spawn_map).after(reset_game_rng)),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(animate_tree));
     }
 }
 
@@ -31,9 +53,22 @@ pub struct Tree {
 #[derive(Component)]
 pub struct TreeTimer(pub Timer);
 
+// A young tree, it can't be chopped until it is fully grown (growth from 0.0 to 1.0)
+#[derive(Component)]
+pub struct Sapling {
+    pub growth: f32,
+}
+
+// time for a sapling to grow into a tree, at the normal growth rate
+const TREE_GROWTH_SECONDS: f32 = 60.0;
+// the scale of a sapling that just sprouted, relative to a tree
+const SAPLING_SCALE: f32 = 0.4;
+
 pub fn spawn_trees(
     mut commands: Commands,
     mut map: ResMut<Map>,
+    clock: Res<GameClock>,
+    mut game_rng: ResMut<GameRng>,
     texture_atlas_handle: Res<AtlasHandle>,
     tree_query: Query<&Transform, (With<Tree>, Without<Player>)>,
     player_query: Query<&Transform, (With<Player>, Without<Tree>)>,
@@ -45,64 +80,186 @@ pub fn spawn_trees(
             break;
         }
 
-        map.push(
-            match spawn_tree(
-                &mut commands,
-                &texture_atlas_handle,
-                &tree_query,
-                &player_query,
-            ) {
-                Some(e) => e,
-                None => continue,
-            },
-        );
+        let tree = match spawn_tree(
+            &mut commands,
+            &map,
+            &mut game_rng,
+            &texture_atlas_handle,
+            &tree_query,
+            &player_query,
+            clock.season(),
+            false,
+        ) {
+            Some(e) => e,
+            None => continue,
+        };
+        map.entities.push(tree);
         tree_amount += 1;
     }
+    let respawn_seconds = clock.season().tree_respawn_seconds().unwrap_or(30.0);
     commands
         .spawn()
-        .insert(TreeTimer(Timer::from_seconds(30.0, true)));
+        .insert(TreeTimer(Timer::from_seconds(respawn_seconds, true)))
+        .insert(InGame);
 }
 
 fn spawn_tree(
     commands: &mut Commands,
+    map: &Map,
+    rng: &mut GameRng,
     texture_atlas_handle: &Res<AtlasHandle>,
     tree_query: &Query<&Transform, (With<Tree>, Without<Player>)>,
     player_query: &Query<&Transform, (With<Player>, Without<Tree>)>,
+    season: Season,
+    sapling: bool,
 ) -> Option<Entity> {
-    let texture_sprite = TextureAtlasSprite::new(15);
-    let mut rng = rand::thread_rng();
-
     let (x, y) = (
         rng.gen_range(-(TILE_COUNT_X as i32)..=TILE_COUNT_X as i32),
         rng.gen_range(-(TILE_COUNT_Y as i32)..=TILE_COUNT_Y as i32),
     );
 
-    if check_tree_position(Vec2::new(x as f32, y as f32), tree_query, player_query) {
+    // trees only grow on grass
+    if !map.can_grow_trees(IVec2::new(x, y))
+        || check_tree_position(Vec2::new(x as f32, y as f32), tree_query, player_query)
+    {
         return None;
     }
+    Some(plant_tree(
+        commands,
+        texture_atlas_handle,
+        IVec2::new(x, y),
+        season,
+        sapling,
+    ))
+}
 
-    Some(
-        commands
-            .spawn_bundle(SpriteSheetBundle {
-                sprite: texture_sprite.clone(),
-                texture_atlas: (*texture_atlas_handle).clone(),
-                transform: Transform::from_scale(Vec3::splat(SCALE)).with_translation(Vec3::new(
-                    x as f32 * SCALE * TILE_SIZE,
-                    y as f32 * SCALE * TILE_SIZE,
-                    20.0,
-                )),
-                ..Default::default()
-            })
-            .insert(Tree { health: 100 })
-            .insert(Animations {
-                animations: vec![Animation {
-                    frames: vec![15, 16, 17, 18, 19],
-                    current_frame: 0,
-                    timer: AnimationTimer(Timer::from_seconds(0.5, true)),
-                }],
-            })
-            .id(),
-    )
+// spawn a tree on a tile, or a sapling which grows into a tree
+pub fn plant_tree(
+    commands: &mut Commands,
+    texture_atlas_handle: &AtlasHandle,
+    tile: IVec2,
+    season: Season,
+    sapling: bool,
+) -> Entity {
+    let frames = season.tree_frames();
+    let texture_sprite = TextureAtlasSprite::new(frames[0]);
+    let scale = if sapling {
+        SCALE * SAPLING_SCALE
+    } else {
+        SCALE
+    };
+
+    let mut tree = commands.spawn_bundle(SpriteSheetBundle {
+        sprite: texture_sprite,
+        texture_atlas: texture_atlas_handle.0.clone(),
+        transform: Transform::from_scale(Vec3::splat(scale))
+            .with_translation(tile_to_world(tile).extend(0.0)),
+        ..Default::default()
+    });
+    tree.insert(Tree {
+        health: TREE_HEALTH,
+    })
+    .insert(Shaded)
+    .insert(YSorted {
+        anchor: TREE_ROOT_Y,
+    })
+    .insert(InGame)
+    .insert(Animations {
+        animations: vec![Animation {
+            frames,
+            current_frame: 0,
+            timer: AnimationTimer(Timer::from_seconds(0.5, true)),
+        }],
+    });
+    if sapling {
+        tree.insert(Sapling { growth: 0.0 });
+    }
+    tree.id()
+}
+
+// the player plants a sapling on the grass tile in front of them
+fn plant_sapling(
+    input: Res<PlayerInput>,
+    clock: Res<GameClock>,
+    game_assets: Res<GameAssets>,
+    texture_atlas_handle: Res<AtlasHandle>,
+    mut map: ResMut<Map>,
+    mut commands: Commands,
+    player_query: Query<(&Transform, &Direction), With<Player>>,
+    tree_query: Query<&Transform, (With<Tree>, Without<Player>)>,
+    mut sapling_res_query: Query<&mut ResourceCounter, With<SaplingResource>>,
+) {
+    if !input.plant {
+        return;
+    }
+    let (player_transform, direction) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let mut sapling_count = sapling_res_query.single_mut();
+    if sapling_count.0 == 0 {
+        return;
+    }
+
+    let tile =
+        world_to_tile(player_feet(player_transform.translation)) + direction.vector().as_ivec2();
+    let occupied = tree_query
+        .iter()
+        .any(|transform| world_to_tile(transform.translation.truncate()) == tile);
+    if !map.can_grow_trees(tile) || occupied {
+        trigger_popup(
+            &mut commands,
+            &game_assets,
+            player_transform.translation + Vec3::new(0.5, 1.8 * TILE_SIZE, 0.0),
+            Popup::text("Can't plant here")
+                .with_color(Color::rgb(1.0, 0.4, 0.4))
+                .with_font_size(14.0),
+        );
+        return;
+    }
+
+    sapling_count.0 -= 1;
+    let tree = plant_tree(
+        &mut commands,
+        &texture_atlas_handle,
+        tile,
+        clock.season(),
+        true,
+    );
+    map.entities.push(tree);
+}
+
+// inflict damage to a tree, it falls away from the one chopping it when it has no health left
+pub fn chop_tree(
+    commands: &mut Commands,
+    hit_events: &mut EventWriter<TreeHit>,
+    progress_events: &mut EventWriter<QuestProgress>,
+    stats: &mut GameStats,
+    tree_entity: Entity,
+    tree: &mut Tree,
+    tree_position: Vec3,
+    damage: u32,
+    chopper_x: f32,
+) -> bool {
+    // already felled by another chopper on this tick, it is removed at the end of the tick
+    if tree.health <= 0 {
+        return false;
+    }
+    tree.health -= damage as i16;
+    hit_events.send(TreeHit(tree_entity));
+    if tree.health > 0 {
+        return false;
+    }
+    let side = (tree_position.x - chopper_x).signum();
+    commands
+        .entity(tree_entity)
+        .remove::<Tree>()
+        .remove::<Burning>()
+        .remove::<Interactable>()
+        .insert(FallingTree::new(side, tree_position));
+    stats.trees_felled += 1;
+    progress_events.send(QuestProgress::TreeFelled);
+    true
 }
 
 pub fn check_tree_position(
@@ -130,17 +287,33 @@ pub fn check_tree_position(
     false
 }
 
+// the trees follow the season of the clock, the frames are swapped when it changes
 fn animate_tree(
     time: Res<Time>,
+    clock: Res<GameClock>,
+    weather: Res<Weather>,
     mut tree_query: Query<(&mut TextureAtlasSprite, &mut Animations), With<Tree>>,
 ) {
+    let frames = clock.season().tree_frames();
+    // the trees sway faster with the wind
+    let delta = time.delta().mul_f32(weather.state.sway_speed());
+
     for (mut sprite, mut animations) in tree_query.iter_mut() {
-        animations.animations[0].update(&time, &mut sprite);
+        let animation = &mut animations.animations[0];
+        if animation.frames != frames {
+            animation.frames = frames.clone();
+            animation.current_frame %= frames.len();
+            sprite.index = frames[animation.current_frame];
+        }
+        animation.update_with_delta(delta, &mut sprite);
     }
 }
 
-fn check_tree_amount(
-    time: Res<Time>,
+// a missing tree grows back as a sapling every few seconds, depending on the season
+pub fn check_tree_amount(
+    tick: Res<FixedTick>,
+    clock: Res<GameClock>,
+    mut game_rng: ResMut<GameRng>,
     texture_atlas_handle: Res<AtlasHandle>,
     mut map: ResMut<Map>,
     mut commands: Commands,
@@ -150,16 +323,50 @@ fn check_tree_amount(
 ) {
     let mut tree_timer = timer_query.single_mut();
 
-    tree_timer.0.tick(time.delta());
+    let respawn_seconds = match clock.season().tree_respawn_seconds() {
+        Some(seconds) => seconds,
+        None => return,
+    };
+    if tree_timer.0.duration().as_secs_f32() != respawn_seconds {
+        tree_timer
+            .0
+            .set_duration(Duration::from_secs_f32(respawn_seconds));
+    }
+    tree_timer.0.tick(tick.delta());
 
     if tree_timer.0.finished() && tree_query.iter().count() < TREE_AMOUNT {
         if let Some(e) = spawn_tree(
             &mut commands,
+            &map,
+            &mut game_rng,
             &texture_atlas_handle,
             &tree_query,
             &player_query,
+            clock.season(),
+            true,
         ) {
-            map.push(e);
+            map.entities.push(e);
+        }
+    }
+}
+
+fn grow_saplings(
+    tick: Res<FixedTick>,
+    clock: Res<GameClock>,
+    weather: Res<Weather>,
+    mut commands: Commands,
+    mut sapling_query: Query<(Entity, &mut Sapling, &mut Transform)>,
+) {
+    let growth_rate = clock.season().growth_rate() * weather.state.growth_multiplier();
+    let growth = tick.delta_seconds() * growth_rate / TREE_GROWTH_SECONDS;
+
+    for (entity, mut sapling, mut transform) in sapling_query.iter_mut() {
+        sapling.growth = (sapling.growth + growth).min(1.0);
+        transform.scale =
+            Vec3::splat(SCALE * (SAPLING_SCALE + (1.0 - SAPLING_SCALE) * sapling.growth));
+
+        if sapling.growth >= 1.0 {
+            commands.entity(entity).remove::<Sapling>();
         }
     }
 }
@@ -0,0 +1,271 @@
+use crate::{
+    clock::Shaded,
+    depth::YSorted,
+    fire::spread_fire,
+    game_state::{AppState, GameRng, GameStats, InGame},
+    interaction::{update_interactions, Interactable, Interacted, InteractionKind},
+    loading::GameAssets,
+    map::TILE_SIZE,
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{Item, ResourceCounter, WoodResource},
+    tick::{FixedTick, FixedUpdateStage},
+    trees::{check_tree_amount, Sapling, Tree},
+    SCALE,
+};
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Weather::default())
+            .add_system_to_stage(FixedUpdateStage, change_weather.after(check_tree_amount))
+            // after the fire, so the trees it sets on fire are not despawned first
+            .add_system_to_stage(FixedUpdateStage, knock_down_trees.after(spread_fire))
+            .add_system_to_stage(
+                FixedUpdateStage,
+                harvest_fallen_logs.after(update_interactions),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(reset_weather)
+                    .with_system(spawn_weather_text),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(update_weather_text),
+            );
+    }
+}
+
+// how long the weather lasts before it (maybe) changes
+const WEATHER_SECONDS: f32 = 60.0;
+// chance for a storm to knock down a tree, per second
+const STORM_KNOCKDOWN_CHANCE: f64 = 0.05;
+// wood given by a fallen log
+const FALLEN_LOG_WOOD: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WeatherState {
+    Clear,
+    Rain,
+    Wind,
+    Storm,
+}
+
+impl WeatherState {
+    // chances of the next weather, in the order: clear, rain, wind, storm
+    fn transitions(self) -> [f32; 4] {
+        match self {
+            WeatherState::Clear => [0.5, 0.25, 0.2, 0.05],
+            WeatherState::Rain => [0.4, 0.35, 0.1, 0.15],
+            WeatherState::Wind => [0.4, 0.2, 0.3, 0.1],
+            WeatherState::Storm => [0.2, 0.5, 0.3, 0.0],
+        }
+    }
+
+    fn next(self, roll: f32) -> Self {
+        let states = [
+            WeatherState::Clear,
+            WeatherState::Rain,
+            WeatherState::Wind,
+            WeatherState::Storm,
+        ];
+        let mut total = 0.0;
+        for (state, chance) in states.iter().zip(self.transitions()) {
+            total += chance;
+            if roll < total {
+                return *state;
+            }
+        }
+        WeatherState::Clear
+    }
+
+    // multiplier on the saplings growth, the rain makes them grow faster
+    pub fn growth_multiplier(self) -> f32 {
+        match self {
+            WeatherState::Rain | WeatherState::Storm => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    // multiplier on the dryness of the season, the rain makes fires less likely
+    pub fn dryness(self) -> f32 {
+        match self {
+            WeatherState::Clear | WeatherState::Wind => 1.0,
+            WeatherState::Rain => 0.2,
+            WeatherState::Storm => 0.5,
+        }
+    }
+
+    // multiplier on how fast the fire spreads
+    pub fn wind_strength(self) -> f32 {
+        match self {
+            WeatherState::Clear | WeatherState::Rain => 1.0,
+            WeatherState::Wind => 2.0,
+            WeatherState::Storm => 1.5,
+        }
+    }
+
+    // multiplier on the speed of the trees animation
+    pub fn sway_speed(self) -> f32 {
+        match self {
+            WeatherState::Clear | WeatherState::Rain => 1.0,
+            WeatherState::Wind => 2.0,
+            WeatherState::Storm => 3.0,
+        }
+    }
+}
+
+pub struct Weather {
+    pub state: WeatherState,
+    // chance for a storm to knock down a tree, per second
+    pub knockdown_chance: f64,
+    timer: Timer,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather {
+            state: WeatherState::Clear,
+            knockdown_chance: STORM_KNOCKDOWN_CHANCE,
+            timer: Timer::from_seconds(WEATHER_SECONDS, true),
+        }
+    }
+}
+
+// A tree knocked down by a storm, the player harvests it for wood
+#[derive(Component)]
+pub struct FallenLog;
+
+#[derive(Component)]
+struct WeatherText;
+
+fn reset_weather(mut weather: ResMut<Weather>) {
+    *weather = Weather::default();
+}
+
+pub fn change_weather(
+    tick: Res<FixedTick>,
+    mut weather: ResMut<Weather>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    weather.timer.tick(tick.delta());
+
+    if weather.timer.just_finished() {
+        let roll = game_rng.gen::<f32>();
+        weather.state = weather.state.next(roll);
+    }
+}
+
+pub fn knock_down_trees(
+    tick: Res<FixedTick>,
+    weather: Res<Weather>,
+    game_assets: Res<GameAssets>,
+    mut game_rng: ResMut<GameRng>,
+    mut commands: Commands,
+    tree_query: Query<(Entity, &Tree, &Transform), Without<Sapling>>,
+) {
+    if weather.state != WeatherState::Storm
+        || !game_rng.gen_bool((weather.knockdown_chance * tick.delta_seconds() as f64).min(1.0))
+    {
+        return;
+    }
+
+    // the trees order in the query depends on the entities, not on the game,
+    // a tree felled on this tick is falling rather than knocked down
+    let mut trees: Vec<(Entity, Vec3)> = tree_query
+        .iter()
+        .filter(|(_, tree, _)| tree.health > 0)
+        .map(|(entity, _, transform)| (entity, transform.translation))
+        .collect();
+    if trees.is_empty() {
+        return;
+    }
+    trees.sort_unstable_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    let (tree, position) = trees[game_rng.gen_range(0..trees.len())];
+
+    commands.entity(tree).despawn();
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: game_assets.wood_log.clone(),
+            transform: Transform::from_scale(Vec3::splat(SCALE))
+                .with_translation(position - Vec3::new(0.0, TILE_SIZE * SCALE * 0.3, 0.0)),
+            ..Default::default()
+        })
+        .insert(FallenLog)
+        .insert(Shaded)
+        .insert(YSorted { anchor: -2.0 })
+        .insert(Interactable {
+            radius: TILE_SIZE * SCALE * 0.6,
+            prompt: game_assets.e_key.clone(),
+            kind: InteractionKind::Harvest,
+        })
+        .insert(InGame);
+}
+
+fn harvest_fallen_logs(
+    game_assets: Res<GameAssets>,
+    mut interacted_events: EventReader<Interacted>,
+    mut stats: ResMut<GameStats>,
+    mut progress_events: EventWriter<QuestProgress>,
+    mut commands: Commands,
+    log_query: Query<&Transform, With<FallenLog>>,
+    mut wood_res_query: Query<&mut ResourceCounter, With<WoodResource>>,
+) {
+    for event in interacted_events.iter() {
+        if event.kind != InteractionKind::Harvest {
+            continue;
+        }
+        let log_transform = match log_query.get(event.entity) {
+            Ok(transform) => transform,
+            Err(_) => continue,
+        };
+
+        wood_res_query.single_mut().0 += FALLEN_LOG_WOOD;
+        stats.wood_chopped += FALLEN_LOG_WOOD;
+        progress_events.send(QuestProgress::ItemCollected(Item::Wood, FALLEN_LOG_WOOD));
+        trigger_popup(
+            &mut commands,
+            &game_assets,
+            log_transform.translation + Vec3::new(0.0, TILE_SIZE * SCALE * 0.5, 0.0),
+            Popup::text(format!("+{} wood", FALLEN_LOG_WOOD))
+                .with_icon(game_assets.wood_log.clone()),
+        );
+        commands.entity(event.entity).despawn();
+    }
+}
+
+fn spawn_weather_text(mut commands: Commands, game_assets: Res<GameAssets>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(15.0),
+                    top: Val::Px(35.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 12.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(WeatherText)
+        .insert(InGame);
+}
+
+fn update_weather_text(weather: Res<Weather>, mut text_query: Query<&mut Text, With<WeatherText>>) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("{:?}", weather.state);
+    }
+}
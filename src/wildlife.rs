@@ -0,0 +1,471 @@
+use crate::{
+    animations::{Animation, AnimationTimer, Animations},
+    clock::{DayPhase, GameClock, Shaded},
+    depth::YSorted,
+    game_state::{AppState, GameRng, InGame},
+    map::{tile_to_world, Map, TILE_COUNT_X, TILE_COUNT_Y, TILE_SIZE},
+    movement::{move_and_slide, steer, Acceleration, Friction, Velocity},
+    pathfinding::Obstacles,
+    player::{tree_collision, Player},
+    texture_atlas::AtlasHandle,
+    tick::{FixedTick, FixedUpdateStage, Interpolated},
+    trees::{Sapling, Tree, TREE_AMOUNT},
+    weather::knock_down_trees,
+    SCALE,
+};
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct WildlifePlugin;
+
+impl Plugin for WildlifePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(FixedUpdateStage, populate_wildlife.after(knock_down_trees))
+            .add_system_to_stage(FixedUpdateStage, update_animals.after(populate_wildlife))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(spawn_wildlife_timer),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(animate_animals));
+    }
+}
+
+// time between two changes of the population, one animal of each kind comes or leaves
+const POPULATION_SECONDS: f32 = 3.0;
+// how far an animal wanders from where it rests, in tiles
+const WANDER_TILES: f32 = 3.0;
+const MAX_WANDER_SECONDS: f32 = 6.0;
+// an animal leaving the map disappears after this time, even when it couldn't reach the edge
+const MAX_LEAVE_SECONDS: f32 = 15.0;
+// how close an animal must get to where it goes to stop
+const ARRIVAL_RADIUS: f32 = 4.0;
+// the animals stand on the same row of their sprite, 8 pixels under its center
+const ANIMAL_FEET_Y: f32 = -8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnimalKind {
+    Deer,
+    Rabbit,
+    Bird,
+}
+
+impl AnimalKind {
+    pub const ALL: [AnimalKind; 3] = [AnimalKind::Deer, AnimalKind::Rabbit, AnimalKind::Bird];
+
+    // the first of its frames in the sprite sheet: walking right (2 frames), walking left
+    // (2 frames), then resting
+    fn first_frame(self) -> usize {
+        match self {
+            AnimalKind::Deer => 45,
+            AnimalKind::Rabbit => 50,
+            AnimalKind::Bird => 55,
+        }
+    }
+
+    // in tiles per second
+    fn walk_speed(self) -> f32 {
+        match self {
+            AnimalKind::Deer => 1.5,
+            AnimalKind::Rabbit => 1.2,
+            AnimalKind::Bird => 0.8,
+        }
+    }
+
+    fn flee_speed(self) -> f32 {
+        match self {
+            AnimalKind::Deer => 4.5,
+            AnimalKind::Rabbit => 4.0,
+            AnimalKind::Bird => 5.0,
+        }
+    }
+
+    // the animal flees when the player comes closer than this, in tiles
+    pub fn flee_radius(self) -> f32 {
+        match self {
+            AnimalKind::Deer => 3.0,
+            AnimalKind::Rabbit => 2.0,
+            AnimalKind::Bird => 2.5,
+        }
+    }
+
+    // how many animals of this kind live on the map: the deer and the birds need the forest,
+    // the rabbits prefer the open grass. Most of them come out at dawn and dusk
+    pub fn population(self, phase: DayPhase, forest_density: f32) -> usize {
+        let (base, habitat) = match self {
+            AnimalKind::Deer => (
+                match phase {
+                    DayPhase::Dawn | DayPhase::Dusk => 2.0,
+                    DayPhase::Day => 1.0,
+                    DayPhase::Night => 0.0,
+                },
+                forest_density,
+            ),
+            AnimalKind::Rabbit => (
+                match phase {
+                    DayPhase::Dawn | DayPhase::Dusk => 4.0,
+                    DayPhase::Day => 2.0,
+                    DayPhase::Night => 1.0,
+                },
+                1.0 - forest_density / 2.0,
+            ),
+            AnimalKind::Bird => (
+                match phase {
+                    DayPhase::Dawn | DayPhase::Day => 3.0,
+                    DayPhase::Dusk => 1.0,
+                    DayPhase::Night => 0.0,
+                },
+                forest_density,
+            ),
+        };
+        (base * habitat).round() as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnimalState {
+    Rest,
+    Wander,
+    Flee,
+    // going away from the map, the animal is despawned at its edge
+    Leave,
+}
+
+// A wild animal: it rests, wanders around, flees from the player and leaves when
+// the map doesn't suit it anymore
+#[derive(Component)]
+pub struct Animal {
+    kind: AnimalKind,
+    state: AnimalState,
+    timer: Timer,
+    destination: Vec2,
+}
+
+impl Animal {
+    pub fn new(kind: AnimalKind) -> Self {
+        Animal {
+            kind,
+            state: AnimalState::Rest,
+            timer: Timer::from_seconds(1.0, false),
+            destination: Vec2::ZERO,
+        }
+    }
+
+    pub fn kind(&self) -> AnimalKind {
+        self.kind
+    }
+
+    pub fn state(&self) -> AnimalState {
+        self.state
+    }
+
+    // the birds fly over everything when they flee or leave, and walk otherwise
+    fn is_flying(&self) -> bool {
+        self.kind == AnimalKind::Bird
+            && matches!(self.state, AnimalState::Flee | AnimalState::Leave)
+    }
+
+    fn change_state(&mut self, state: AnimalState, seconds: f32) {
+        self.state = state;
+        self.timer = Timer::from_seconds(seconds, false);
+    }
+}
+
+#[derive(Component)]
+pub struct WildlifeTimer(pub Timer);
+
+fn spawn_wildlife_timer(mut commands: Commands) {
+    commands
+        .spawn()
+        .insert(WildlifeTimer(Timer::from_seconds(POPULATION_SECONDS, true)))
+        .insert(InGame);
+}
+
+pub fn spawn_animal(
+    commands: &mut Commands,
+    texture_atlas_handle: &AtlasHandle,
+    kind: AnimalKind,
+    position: Vec2,
+) -> Entity {
+    let first = kind.first_frame();
+    let position = position.extend(0.0);
+    let walk_speed = kind.walk_speed() * TILE_SIZE * SCALE;
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite::new(first + 4),
+            texture_atlas: texture_atlas_handle.0.clone(),
+            transform: Transform::from_scale(Vec3::splat(SCALE)).with_translation(position),
+            ..Default::default()
+        })
+        .insert(Animal::new(kind))
+        .insert(Shaded)
+        .insert(InGame)
+        .insert(Interpolated::new(position))
+        .insert(YSorted {
+            anchor: ANIMAL_FEET_Y,
+        })
+        .insert(Velocity::default())
+        // the animals start and stop in a fifth of a second at their walking speed
+        .insert(Acceleration(walk_speed * 5.0))
+        .insert(Friction(walk_speed * 5.0))
+        .insert(Animations {
+            animations: vec![
+                // index 0: moving->right
+                Animation {
+                    frames: vec![first, first + 1],
+                    current_frame: 0,
+                    timer: AnimationTimer(Timer::from_seconds(0.15, true)),
+                },
+                // index 1: moving->left
+                Animation {
+                    frames: vec![first + 2, first + 3],
+                    current_frame: 0,
+                    timer: AnimationTimer(Timer::from_seconds(0.15, true)),
+                },
+            ],
+        })
+        .id()
+}
+
+// from the center of the map to its edges
+fn map_half_size() -> Vec2 {
+    Vec2::new(TILE_COUNT_X as f32, TILE_COUNT_Y as f32) * TILE_SIZE * SCALE
+}
+
+fn is_outside_map(position: Vec3) -> bool {
+    let half_size = map_half_size();
+    position.x.abs() > half_size.x || position.y.abs() > half_size.y
+}
+
+// the closest point on the edge of the map
+fn closest_edge(position: Vec2) -> Vec2 {
+    let half_size = map_half_size();
+    let to_edge = half_size - position.abs();
+    if to_edge.x < to_edge.y {
+        Vec2::new(half_size.x * position.x.signum(), position.y)
+    } else {
+        Vec2::new(position.x, half_size.y * position.y.signum())
+    }
+}
+
+// every few seconds, an animal of each kind comes from the edge of the map when there are
+// too few of them, or leaves when there are too many
+fn populate_wildlife(
+    tick: Res<FixedTick>,
+    clock: Res<GameClock>,
+    map: Res<Map>,
+    texture_atlas_handle: Res<AtlasHandle>,
+    mut game_rng: ResMut<GameRng>,
+    mut commands: Commands,
+    mut timer_query: Query<&mut WildlifeTimer>,
+    tree_query: Query<(&Transform, Option<&Sapling>), With<Tree>>,
+    mut animal_query: Query<(&mut Animal, &Transform)>,
+) {
+    let mut timer = match timer_query.get_single_mut() {
+        Ok(timer) => timer,
+        Err(_) => return,
+    };
+    if !timer.0.tick(tick.delta()).just_finished() {
+        return;
+    }
+
+    let grown_trees = tree_query
+        .iter()
+        .filter(|(_, sapling)| sapling.is_none())
+        .count();
+    let forest_density = (grown_trees as f32 / TREE_AMOUNT as f32).min(1.0);
+    let obstacles = Obstacles::new(
+        &map,
+        tree_query
+            .iter()
+            .map(|(transform, _)| transform.translation),
+    );
+
+    for kind in AnimalKind::ALL {
+        let wanted = kind.population(clock.phase(), forest_density);
+        // the query order depends on the entities, the animals are sorted by position instead
+        let mut animals: Vec<(Mut<Animal>, Vec2)> = animal_query
+            .iter_mut()
+            .filter(|(animal, _)| animal.kind == kind && animal.state != AnimalState::Leave)
+            .map(|(animal, transform)| (animal, transform.translation.truncate()))
+            .collect();
+        animals.sort_unstable_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+        if animals.len() > wanted {
+            let (animal, position) = &mut animals[0];
+            animal.change_state(AnimalState::Leave, MAX_LEAVE_SECONDS);
+            animal.destination = closest_edge(*position);
+        } else if animals.len() < wanted {
+            // the animal comes from a tile on the edge of the map, where nothing is in its way
+            let tile = if game_rng.gen_bool(0.5) {
+                IVec2::new(
+                    game_rng.gen_range(-(TILE_COUNT_X as i32)..=TILE_COUNT_X as i32),
+                    TILE_COUNT_Y as i32 * if game_rng.gen_bool(0.5) { 1 } else { -1 },
+                )
+            } else {
+                IVec2::new(
+                    TILE_COUNT_X as i32 * if game_rng.gen_bool(0.5) { 1 } else { -1 },
+                    game_rng.gen_range(-(TILE_COUNT_Y as i32)..=TILE_COUNT_Y as i32),
+                )
+            };
+            if obstacles.is_walkable(tile) {
+                spawn_animal(
+                    &mut commands,
+                    &texture_atlas_handle,
+                    kind,
+                    tile_to_world(tile),
+                );
+            }
+        }
+    }
+}
+
+// - the animals flee from the player when it comes too close
+// - a resting animal wanders to a random spot around it after a while
+// - the animals walking on the ground are blocked by the water and the trees
+fn update_animals(
+    tick: Res<FixedTick>,
+    map: Res<Map>,
+    mut game_rng: ResMut<GameRng>,
+    mut commands: Commands,
+    player_query: Query<&Transform, (With<Player>, Without<Animal>)>,
+    tree_query: Query<&Transform, (With<Tree>, Without<Animal>)>,
+    mut animal_query: Query<(
+        Entity,
+        &mut Animal,
+        &mut Transform,
+        &mut Velocity,
+        &Acceleration,
+        &Friction,
+    )>,
+) {
+    let player_pos = player_query
+        .get_single()
+        .map(|transform| transform.translation.truncate())
+        .ok();
+
+    // the animals use the random number generator in the order of their positions
+    let mut animals: Vec<(Entity, Vec3)> = animal_query
+        .iter()
+        .map(|(entity, _, transform, ..)| (entity, transform.translation))
+        .collect();
+    animals.sort_unstable_by(|(a, a_pos), (b, b_pos)| {
+        a_pos
+            .x
+            .total_cmp(&b_pos.x)
+            .then(a_pos.y.total_cmp(&b_pos.y))
+            .then(a.cmp(b))
+    });
+
+    for (entity, _) in animals {
+        let (_, mut animal, mut transform, mut velocity, acceleration, friction) =
+            animal_query.get_mut(entity).unwrap();
+        let position = transform.translation.truncate();
+        let kind = animal.kind;
+        let flee_radius = kind.flee_radius() * TILE_SIZE * SCALE;
+        let player_distance = player_pos.map_or(f32::INFINITY, |player| player.distance(position));
+        animal.timer.tick(tick.delta());
+
+        match animal.state {
+            AnimalState::Rest | AnimalState::Wander if player_distance < flee_radius => {
+                animal.change_state(AnimalState::Flee, 0.0);
+            }
+            AnimalState::Flee if player_distance > 2.0 * flee_radius => {
+                let seconds = game_rng.gen_range(1.0..3.0);
+                animal.change_state(AnimalState::Rest, seconds);
+            }
+            AnimalState::Rest if animal.timer.finished() => {
+                let offset =
+                    Vec2::new(game_rng.gen_range(-1.0..1.0), game_rng.gen_range(-1.0..1.0))
+                        * WANDER_TILES
+                        * TILE_SIZE
+                        * SCALE;
+                let half_size = map_half_size() - TILE_SIZE * SCALE;
+                animal.destination = (position + offset).clamp(-half_size, half_size);
+                animal.change_state(AnimalState::Wander, MAX_WANDER_SECONDS);
+            }
+            AnimalState::Wander
+                if animal.timer.finished()
+                    || animal.destination.distance(position) < ARRIVAL_RADIUS =>
+            {
+                let seconds = game_rng.gen_range(1.0..4.0);
+                animal.change_state(AnimalState::Rest, seconds);
+            }
+            AnimalState::Leave
+                if animal.timer.finished()
+                    || animal.destination.distance(position) < ARRIVAL_RADIUS =>
+            {
+                commands.entity(entity).despawn();
+                continue;
+            }
+            _ => {}
+        }
+
+        let speed = match animal.state {
+            AnimalState::Flee => kind.flee_speed(),
+            AnimalState::Leave if animal.is_flying() => kind.flee_speed(),
+            _ => kind.walk_speed(),
+        } * TILE_SIZE
+            * SCALE;
+        let wanted_velocity = match (animal.state, player_pos) {
+            (AnimalState::Flee, Some(player)) => (position - player).normalize_or_zero() * speed,
+            (AnimalState::Wander | AnimalState::Leave, _) => {
+                (animal.destination - position).normalize_or_zero() * speed
+            }
+            _ => Vec2::ZERO,
+        };
+        // the flight is faster than the walk, the animal speeds up as much
+        let acceleration = acceleration.0 * speed / (kind.walk_speed() * TILE_SIZE * SCALE);
+
+        steer(
+            &mut velocity,
+            wanted_velocity,
+            acceleration,
+            friction.0,
+            tick.delta_seconds(),
+        );
+        let flying = animal.is_flying();
+        let leaving = animal.state == AnimalState::Leave;
+        move_and_slide(
+            &mut transform.translation,
+            &mut velocity,
+            tick.delta_seconds(),
+            |target| {
+                let feet = target.truncate() + Vec2::new(0.0, ANIMAL_FEET_Y * SCALE);
+                // the animals leaving the map are let out of it
+                (is_outside_map(target) && !leaving)
+                    || (!flying
+                        && (!map.is_walkable(feet)
+                            || tree_collision(
+                                target,
+                                tree_query.iter().map(|transform| transform.translation),
+                            )))
+            },
+        );
+    }
+}
+
+// the animals walk or fly according to their velocity, and show their resting frame
+// when they stop (a bird on the ground hops without flapping its wings). The resting
+// frame faces right, it is flipped when the animal goes left
+fn animate_animals(
+    time: Res<Time>,
+    mut query: Query<(&Animal, &Velocity, &mut TextureAtlasSprite, &mut Animations)>,
+) {
+    for (animal, velocity, mut sprite, mut animations) in query.iter_mut() {
+        let moving = velocity.0.length() > 1.0;
+        if !moving || (animal.kind == AnimalKind::Bird && !animal.is_flying()) {
+            sprite.index = animal.kind.first_frame() + 4;
+            if velocity.0.x != 0.0 {
+                sprite.flip_x = velocity.0.x < 0.0;
+            }
+            continue;
+        }
+        sprite.flip_x = false;
+        let animation = if velocity.0.x < 0.0 {
+            &mut animations.animations[1]
+        } else {
+            &mut animations.animations[0]
+        };
+        animation.update(&time, &mut sprite);
+    }
+}
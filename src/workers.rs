@@ -0,0 +1,467 @@
+use crate::{
+    clock::{Shaded, Tint},
+    depth::YSorted,
+    fire::{spread_fire, Burning},
+    footsteps::{leave_footsteps, Footsteps},
+    game_state::{AppState, GameStats, InGame},
+    interaction::{update_interactions, Interactable, Interacted, InteractionKind},
+    loading::GameAssets,
+    map::{tile_to_world, world_to_tile, Map, MapLayout, TILE_SIZE},
+    movement::{move_and_slide, steer, Acceleration, Friction, Velocity},
+    pathfinding::{find_path, path_to_tree, waypoints, Obstacles, PathFollower},
+    player::{
+        character_animations, chop_wood_action, player_feet, tree_collision, Direction,
+        PlayerState, Speed, Strength, PLAYER_HALF_SIZE_Y,
+    },
+    popup::{trigger_popup, Popup},
+    quests::QuestProgress,
+    resource_counter::{CoinResource, Item, ResourceCounter, WoodResource},
+    texture_atlas::AtlasHandle,
+    tick::{FixedTick, FixedUpdateStage, Interpolated},
+    tree_feedback::TreeHit,
+    trees::{chop_tree, Sapling, Tree},
+    SCALE,
+};
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct WorkersPlugin;
+
+impl Plugin for WorkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(FixedUpdateStage, hire_workers.after(update_interactions))
+            .add_system_to_stage(
+                FixedUpdateStage,
+                update_workers.after(chop_wood_action).before(spread_fire),
+            )
+            .add_system_to_stage(
+                FixedUpdateStage,
+                move_workers.after(update_workers).before(leave_footsteps),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_lumber_camps));
+    }
+}
+
+pub const WORKER_PRICE: u32 = 30;
+pub const MAX_WORKERS: usize = 3;
+// the wood a worker carries before bringing it to the camp
+pub const WORKER_CAPACITY: u32 = 5;
+const WORKER_STRENGTH: u32 = 20;
+// time between two swings of the axe, and how long the axe is shown down
+const SWING_SECONDS: f32 = 0.6;
+const SWING_SHOWN_SECONDS: f32 = 0.2;
+// time an idle worker waits before looking for a tree again
+const IDLE_SECONDS: f32 = 0.5;
+// a worker walking longer than this is stuck (a sapling grew on its path), it thinks again
+const MAX_WALK_SECONDS: f32 = 20.0;
+// only the closest trees are tried when looking for a path to one
+const TREES_TRIED: usize = 5;
+
+// Where the workers are hired, and where they bring the wood they chopped
+#[derive(Component)]
+pub struct LumberCamp;
+
+// What a worker is doing:
+// - Idle: looks for the closest tree it can reach, or brings its wood when there is none
+// - Seek: walks to the tree
+// - Chop: chops the tree until it falls or the worker can't carry more wood
+// - Deliver: walks to the lumber camp and adds its wood to the player's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WorkerState {
+    Idle,
+    Seek,
+    Chop,
+    Deliver,
+}
+
+// A lumberjack hired by the player, chopping the trees on its own
+#[derive(Component)]
+pub struct Worker {
+    state: WorkerState,
+    wood: u32,
+    capacity: u32,
+    target: Option<Entity>,
+    // the wait of the current state: before thinking again, between two swings, or
+    // before giving up on a walk
+    timer: Timer,
+}
+
+impl Worker {
+    pub fn new(capacity: u32) -> Self {
+        Worker {
+            state: WorkerState::Idle,
+            wood: 0,
+            capacity,
+            target: None,
+            timer: Timer::from_seconds(IDLE_SECONDS, false),
+        }
+    }
+
+    pub fn state(&self) -> WorkerState {
+        self.state
+    }
+
+    // the wood carried, not delivered yet
+    pub fn wood(&self) -> u32 {
+        self.wood
+    }
+
+    fn is_full(&self) -> bool {
+        self.wood >= self.capacity
+    }
+
+    fn change_state(&mut self, state: WorkerState, seconds: f32) {
+        self.state = state;
+        self.timer = Timer::from_seconds(seconds, false);
+    }
+
+    // think again on the next tick
+    fn idle_now(&mut self) {
+        self.change_state(WorkerState::Idle, 0.0);
+        self.target = None;
+    }
+}
+
+fn spawn_lumber_camps(
+    game_assets: Res<GameAssets>,
+    map_layout: Res<MapLayout>,
+    mut commands: Commands,
+) {
+    for tile in map_layout.lumber_camps.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: game_assets.lumber_camp.clone(),
+                transform: Transform::from_scale(Vec3::splat(SCALE * 0.5))
+                    .with_translation(tile_to_world(*tile).extend(0.0)),
+                ..Default::default()
+            })
+            .insert(LumberCamp)
+            .insert(Shaded)
+            .insert(YSorted { anchor: -14.0 })
+            .insert(Interactable {
+                radius: TILE_SIZE * SCALE * 0.6,
+                prompt: game_assets.e_key.clone(),
+                kind: InteractionKind::Hire,
+            })
+            .insert(InGame);
+    }
+}
+
+// a worker costs coins, and the camp only has room for a few of them
+fn hire_workers(
+    game_assets: Res<GameAssets>,
+    texture_atlas_handle: Res<AtlasHandle>,
+    mut interacted_events: EventReader<Interacted>,
+    mut commands: Commands,
+    camp_query: Query<&Transform, With<LumberCamp>>,
+    worker_query: Query<(), With<Worker>>,
+    mut coins_res_query: Query<&mut ResourceCounter, With<CoinResource>>,
+) {
+    // the workers hired on this tick are not spawned yet
+    let mut worker_count = worker_query.iter().count();
+
+    for event in interacted_events.iter() {
+        if event.kind != InteractionKind::Hire {
+            continue;
+        }
+        let camp_position = match camp_query.get(event.entity) {
+            Ok(transform) => transform.translation,
+            Err(_) => continue,
+        };
+        let popup_position = camp_position + Vec3::new(0.0, TILE_SIZE * SCALE, 0.0);
+        let mut coins_count = coins_res_query.single_mut();
+
+        let refusal = if worker_count >= MAX_WORKERS {
+            Some("No room left!".to_string())
+        } else if coins_count.0 < WORKER_PRICE {
+            Some(format!("{} coins needed", WORKER_PRICE))
+        } else {
+            None
+        };
+        if let Some(refusal) = refusal {
+            trigger_popup(
+                &mut commands,
+                &game_assets,
+                popup_position,
+                Popup::text(refusal)
+                    .with_color(Color::rgb(1.0, 0.4, 0.4))
+                    .with_font_size(14.0),
+            );
+            continue;
+        }
+
+        coins_count.0 -= WORKER_PRICE;
+        worker_count += 1;
+        spawn_worker(&mut commands, &texture_atlas_handle, camp_position);
+        trigger_popup(
+            &mut commands,
+            &game_assets,
+            popup_position,
+            Popup::text(format!("-{} coins", WORKER_PRICE))
+                .with_icon(game_assets.coin.clone())
+                .with_color(Color::rgb(1.0, 0.85, 0.3)),
+        );
+    }
+}
+
+// a worker looks like the player in other clothes, it is a bit slower and weaker
+pub fn spawn_worker(commands: &mut Commands, texture_atlas_handle: &AtlasHandle, position: Vec3) {
+    let position = position.truncate().extend(0.0);
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.0.clone(),
+            transform: Transform::from_scale(Vec3::splat(SCALE)).with_translation(position),
+            ..Default::default()
+        })
+        .insert(Worker::new(WORKER_CAPACITY))
+        .insert(Shaded)
+        .insert(Tint(Color::rgb(1.0, 0.75, 0.55)))
+        .insert(InGame)
+        .insert(Interpolated::new(position))
+        .insert(YSorted {
+            anchor: -PLAYER_HALF_SIZE_Y,
+        })
+        .insert(Strength(WORKER_STRENGTH))
+        .insert(Speed(2.5 * TILE_SIZE * SCALE))
+        .insert(Velocity::default())
+        .insert(PathFollower::default())
+        .insert(Acceleration(30.0 * TILE_SIZE * SCALE))
+        .insert(Friction(30.0 * TILE_SIZE * SCALE))
+        .insert(Footsteps::new(player_feet(Vec3::ZERO)))
+        .insert(PlayerState::Stand(Direction::Right))
+        .insert(Direction::Right)
+        .insert(character_animations());
+}
+
+// the state machine of the workers, they are updated in the order of their entities so two
+// workers never race for the same tree differently
+pub fn update_workers(
+    tick: Res<FixedTick>,
+    map: Res<Map>,
+    game_assets: Res<GameAssets>,
+    mut stats: ResMut<GameStats>,
+    mut commands: Commands,
+    mut hit_events: EventWriter<TreeHit>,
+    mut progress_events: EventWriter<QuestProgress>,
+    mut worker_query: Query<(
+        Entity,
+        &mut Worker,
+        &Transform,
+        &Strength,
+        &mut PathFollower,
+        &mut PlayerState,
+        &mut Direction,
+    )>,
+    obstacle_query: Query<&Transform, (With<Tree>, Without<Worker>)>,
+    mut tree_query: Query<
+        (Entity, &mut Tree, &Transform),
+        (Without<Sapling>, Without<Burning>, Without<Worker>),
+    >,
+    camp_query: Query<&Transform, (With<LumberCamp>, Without<Worker>)>,
+    mut wood_res_query: Query<&mut ResourceCounter, With<WoodResource>>,
+) {
+    let mut workers: Vec<Entity> = worker_query.iter().map(|(entity, ..)| entity).collect();
+    workers.sort_unstable();
+
+    for entity in workers {
+        let (_, mut worker, transform, strength, mut follower, mut state, mut direction) =
+            worker_query.get_mut(entity).unwrap();
+        let position = transform.translation;
+        worker.timer.tick(tick.delta());
+
+        match worker.state {
+            WorkerState::Idle => {
+                if !worker.timer.finished() {
+                    continue;
+                }
+                worker.timer = Timer::from_seconds(IDLE_SECONDS, false);
+                let obstacles = Obstacles::new(
+                    &map,
+                    obstacle_query.iter().map(|transform| transform.translation),
+                );
+                let start = world_to_tile(position.truncate());
+
+                if !worker.is_full() {
+                    let mut trees: Vec<(Entity, Vec3)> = tree_query
+                        .iter()
+                        .map(|(tree, _, transform)| (tree, transform.translation))
+                        .collect();
+                    trees.sort_unstable_by(|(a, a_pos), (b, b_pos)| {
+                        let a_distance = a_pos.distance_squared(position);
+                        let b_distance = b_pos.distance_squared(position);
+                        a_distance.total_cmp(&b_distance).then(a.cmp(b))
+                    });
+                    let path = trees.iter().take(TREES_TRIED).find_map(|(tree, tree_pos)| {
+                        path_to_tree(&obstacles, start, position.x, *tree_pos)
+                            .map(|path| (*tree, path))
+                    });
+                    if let Some((tree, path)) = path {
+                        worker.target = Some(tree);
+                        worker.change_state(WorkerState::Seek, MAX_WALK_SECONDS);
+                        follower.follow(path);
+                        continue;
+                    }
+                }
+                // full, or no tree to chop: the wood is brought to the closest camp
+                if worker.wood > 0 {
+                    let camp = camp_query
+                        .iter()
+                        .map(|transform| transform.translation.truncate())
+                        .min_by(|a, b| {
+                            a.distance_squared(position.truncate())
+                                .total_cmp(&b.distance_squared(position.truncate()))
+                        });
+                    let path = camp.and_then(|camp| {
+                        find_path(start, world_to_tile(camp), |tile| {
+                            obstacles.is_walkable(tile)
+                        })
+                        .map(|path| waypoints(&path, camp))
+                    });
+                    if let Some(path) = path {
+                        worker.change_state(WorkerState::Deliver, MAX_WALK_SECONDS);
+                        follower.follow(path);
+                    }
+                }
+            }
+            WorkerState::Seek => {
+                let tree_exists = worker
+                    .target
+                    .map_or(false, |tree| tree_query.get(tree).is_ok());
+                if !tree_exists || worker.timer.finished() {
+                    follower.clear();
+                    worker.idle_now();
+                } else if follower.is_done() {
+                    // the first swing is right away
+                    worker.change_state(WorkerState::Chop, SWING_SECONDS);
+                    worker.timer.tick(Duration::from_secs_f32(SWING_SECONDS));
+                }
+            }
+            WorkerState::Chop => {
+                // a tree felled on this tick by another chopper is gone too
+                let tree = worker
+                    .target
+                    .and_then(|tree| tree_query.get_mut(tree).ok())
+                    .filter(|(_, tree, _)| tree.health > 0);
+                let (tree_entity, mut tree, tree_transform) = match tree {
+                    Some(tree) => tree,
+                    None => {
+                        *state = PlayerState::Stand(*direction);
+                        worker.idle_now();
+                        continue;
+                    }
+                };
+                *direction =
+                    Direction::facing(Vec2::new(tree_transform.translation.x - position.x, 0.0));
+                *state = if worker.timer.elapsed_secs() < SWING_SHOWN_SECONDS {
+                    PlayerState::Chop(*direction)
+                } else {
+                    PlayerState::Stand(*direction)
+                };
+                if !worker.timer.finished() {
+                    continue;
+                }
+                worker.timer.reset();
+                *state = PlayerState::Chop(*direction);
+
+                worker.wood += 1;
+                stats.wood_chopped += 1;
+                let felled = chop_tree(
+                    &mut commands,
+                    &mut hit_events,
+                    &mut progress_events,
+                    &mut stats,
+                    tree_entity,
+                    &mut tree,
+                    tree_transform.translation,
+                    strength.0,
+                    position.x,
+                );
+                if felled || worker.is_full() {
+                    worker.idle_now();
+                }
+            }
+            WorkerState::Deliver => {
+                if worker.timer.finished() {
+                    follower.clear();
+                    worker.idle_now();
+                } else if follower.is_done() {
+                    wood_res_query.single_mut().0 += worker.wood;
+                    progress_events.send(QuestProgress::ItemCollected(Item::Wood, worker.wood));
+                    trigger_popup(
+                        &mut commands,
+                        &game_assets,
+                        position + Vec3::new(0.5, 1.8 * TILE_SIZE, 0.0),
+                        Popup::text(format!("+{}", worker.wood))
+                            .with_icon(game_assets.wood_log.clone()),
+                    );
+                    worker.wood = 0;
+                    worker.idle_now();
+                }
+            }
+        }
+    }
+}
+
+// the workers walk along their path like the player, blocked by the water and the trees
+pub fn move_workers(
+    tick: Res<FixedTick>,
+    map: Res<Map>,
+    tree_query: Query<&Transform, (With<Tree>, Without<Worker>)>,
+    mut worker_query: Query<(
+        &Worker,
+        &mut Transform,
+        &Speed,
+        &mut Velocity,
+        &Acceleration,
+        &Friction,
+        &mut PathFollower,
+        &mut PlayerState,
+        &mut Direction,
+    )>,
+) {
+    for (
+        worker,
+        mut transform,
+        speed,
+        mut velocity,
+        acceleration,
+        friction,
+        mut follower,
+        mut state,
+        mut direction,
+    ) in worker_query.iter_mut()
+    {
+        let speed = speed.0 * map.speed_multiplier(player_feet(transform.translation));
+        let wanted_velocity =
+            follower.wanted_velocity(transform.translation.truncate(), speed, friction.0);
+
+        if worker.state != WorkerState::Chop {
+            *state = if wanted_velocity == Vec2::ZERO {
+                PlayerState::Stand(*direction)
+            } else {
+                *direction = Direction::facing(wanted_velocity);
+                PlayerState::Move(*direction)
+            };
+        }
+
+        steer(
+            &mut velocity,
+            wanted_velocity,
+            acceleration.0,
+            friction.0,
+            tick.delta_seconds(),
+        );
+        move_and_slide(
+            &mut transform.translation,
+            &mut velocity,
+            tick.delta_seconds(),
+            |target| {
+                !map.is_walkable(player_feet(target))
+                    || tree_collision(
+                        target,
+                        tree_query.iter().map(|transform| transform.translation),
+                    )
+            },
+        );
+    }
+}
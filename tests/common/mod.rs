@@ -0,0 +1,94 @@
+// the helpers shared by the tests, each test file only uses some of them
+#![allow(dead_code)]
+
+use bevy::{
+    ecs::event::Events,
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use bevy_game::{
+    game_state::GameRng,
+    headless::headless_app,
+    map::tile_to_world,
+    player::{Player, PLAYER_HALF_SIZE_Y},
+    resource_counter::ResourceCounter,
+    tick::Interpolated,
+    trees::Tree,
+    SCALE,
+};
+
+// the headless app with a started game
+pub fn new_game() -> App {
+    new_game_with(|_| {})
+}
+
+// the headless app with a started game, 'setup' changes the app before the game starts
+pub fn new_game_with(setup: impl FnOnce(&mut App)) -> App {
+    let mut app = headless_app();
+    setup(&mut app);
+    // the first frames run until the game is started
+    run_frames(&mut app, 3);
+    app
+}
+
+// the started game places everything randomly from the seed
+pub fn new_game_with_seed(seed: u64) -> App {
+    new_game_with(|app| app.world.resource_mut::<GameRng>().next_seed = Some(seed))
+}
+
+pub fn run_frames(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+pub fn counter<T: Component>(app: &mut App) -> &mut ResourceCounter {
+    app.world
+        .query_filtered::<&mut ResourceCounter, With<T>>()
+        .iter_mut(&mut app.world)
+        .next()
+        .unwrap()
+        .into_inner()
+}
+
+pub fn player(app: &mut App) -> Entity {
+    app.world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap()
+}
+
+// move the player so that its feet are on the tile
+pub fn move_player(app: &mut App, tile: IVec2) {
+    let position = (tile_to_world(tile) + Vec2::new(0.0, PLAYER_HALF_SIZE_Y * SCALE)).extend(0.0);
+    let player = player(app);
+    app.world
+        .entity_mut(player)
+        .insert(Transform::from_translation(position).with_scale(Vec3::splat(SCALE)))
+        .insert(Interpolated::new(position));
+    app.update();
+}
+
+// despawn the randomly placed trees, so nothing is in the way of the player
+pub fn remove_trees(app: &mut App) {
+    let trees: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<Tree>>()
+        .iter(&app.world)
+        .collect();
+    for tree in trees {
+        app.world.despawn(tree);
+    }
+}
+
+pub fn press_keys(app: &mut App, key_codes: &[KeyCode], state: ElementState) {
+    let mut events = app.world.resource_mut::<Events<KeyboardInput>>();
+    for key_code in key_codes {
+        events.send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(*key_code),
+            state,
+        });
+    }
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_game::{
+    dialogue::{Conversation, DialogueError, DialogueFlags, Dialogues, OpenDialogue},
+    headless::headless_app,
+    map::MapLayout,
+    player::PlayerInput,
+    resource_counter::{CoinResource, SaplingResource, WoodResource},
+};
+use common::{counter, move_player, new_game};
+
+fn interact(app: &mut App) {
+    app.world.resource_mut::<PlayerInput>().interact = true;
+    app.update();
+}
+
+fn choose(app: &mut App, choice: u8) {
+    app.world.resource_mut::<PlayerInput>().choice = Some(choice);
+    app.update();
+}
+
+// the name of the node the conversation is at, once its text is typed
+fn wait_for_node(app: &mut App) -> Option<String> {
+    for _ in 0..10 * 60 {
+        match &app.world.resource::<OpenDialogue>().0 {
+            Some(dialogue) if dialogue.is_typing() => app.update(),
+            _ => break,
+        }
+    }
+    let dialogue = app.world.resource::<OpenDialogue>().0.clone()?;
+    assert!(!dialogue.is_typing());
+    let conversation = app
+        .world
+        .resource::<Dialogues>()
+        .get(dialogue.conversation())
+        .unwrap();
+    Some(conversation.nodes[dialogue.node()].name.clone())
+}
+
+#[test]
+fn dialogue_files_are_parsed() {
+    let app = headless_app();
+    let dialogues = app.world.resource::<Dialogues>();
+    let forester = dialogues.get("forester").unwrap();
+    assert_eq!(forester.speaker, "Old forester");
+    assert_eq!(forester.nodes[0].name, "start");
+    assert_eq!(forester.nodes[0].conditions.len(), 1);
+    assert_eq!(forester.nodes[0].choices.len(), 2);
+    assert!(dialogues.get("notice_board").is_some());
+
+    let conversation = Conversation::parse(
+        "speaker Guard\nnode start\nsay Halt!\nsay Who goes there?\nchoice end A friend.\nif coins >= 5",
+    )
+    .unwrap();
+    assert_eq!(conversation.nodes[0].text, "Halt!\nWho goes there?");
+    assert_eq!(conversation.nodes[0].choices[0].text, "A friend.");
+    assert_eq!(conversation.nodes[0].choices[0].conditions.len(), 1);
+
+    assert!(matches!(
+        Conversation::parse("node start\nchoice nowhere Let's go."),
+        Err(DialogueError::UnknownNode(_))
+    ));
+    assert!(matches!(
+        Conversation::parse("node start\nif wood > 3"),
+        Err(DialogueError::Line(_))
+    ));
+    // the flags are words of the save file
+    assert!(matches!(
+        Conversation::parse("node start\ndo set Met-Guard"),
+        Err(DialogueError::Line(_))
+    ));
+    assert!(matches!(
+        Conversation::parse("say Hello"),
+        Err(DialogueError::Line(_))
+    ));
+    assert!(matches!(
+        Conversation::parse("# nothing"),
+        Err(DialogueError::Empty)
+    ));
+}
+
+#[test]
+fn forester_trades_logs_for_saplings() {
+    let mut app = new_game();
+    let (forester, _) = app.world.resource::<MapLayout>().villagers[0].clone();
+    move_player(&mut app, forester);
+
+    // the text is typed one letter at a time, interacting again skips the typing
+    interact(&mut app);
+    for _ in 0..10 {
+        app.update();
+    }
+    let dialogue = app.world.resource::<OpenDialogue>().0.clone().unwrap();
+    assert!(dialogue.is_typing());
+    assert!(dialogue.typed() > 0);
+    interact(&mut app);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "start");
+    assert!(app
+        .world
+        .resource::<DialogueFlags>()
+        .contains("met_forester"));
+
+    // the logs can't be given without having them
+    choose(&mut app, 0);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "help");
+    choose(&mut app, 0);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "help");
+
+    counter::<WoodResource>(&mut app).0 = 12;
+    choose(&mut app, 0);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "thanks");
+    assert_eq!(counter::<WoodResource>(&mut app).0, 2);
+    assert_eq!(counter::<SaplingResource>(&mut app).0, 3);
+
+    // then the forester sells saplings
+    counter::<CoinResource>(&mut app).0 = 20;
+    choose(&mut app, 0);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "shop");
+    choose(&mut app, 0);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "bought");
+    choose(&mut app, 0);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "bought");
+    assert_eq!(counter::<CoinResource>(&mut app).0, 5);
+    assert_eq!(counter::<SaplingResource>(&mut app).0, 4);
+
+    choose(&mut app, 1);
+    assert!(wait_for_node(&mut app).is_none());
+
+    // the next conversation starts where the flags say
+    interact(&mut app);
+    assert_eq!(wait_for_node(&mut app).unwrap(), "start");
+    let dialogue = app.world.resource::<OpenDialogue>().0.clone().unwrap();
+    assert_eq!(dialogue.node(), 1);
+
+    // walking away ends it
+    move_player(&mut app, forester + IVec2::new(4, 0));
+    assert!(app.world.resource::<OpenDialogue>().0.is_none());
+}
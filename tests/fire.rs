@@ -0,0 +1,141 @@
+mod common;
+
+use bevy::{ecs::event::Events, input::ElementState, prelude::*};
+use bevy_game::{
+    fire::{Burning, CharredStump, IgniteTree},
+    map::{tile_to_world, TILE_SIZE},
+    trading_post::TradingPost,
+    tree_feedback::TreeHit,
+    trees::Tree,
+    SCALE,
+};
+use common::{new_game_with_seed, press_keys, remove_trees, run_frames};
+
+// a game with a 5x5 square of trees instead of the randomly placed ones
+fn new_game_with_forest(seed: u64) -> App {
+    let mut app = new_game_with_seed(seed);
+    remove_trees(&mut app);
+    for y in 2..7 {
+        for x in 2..7 {
+            app.world
+                .spawn()
+                .insert(Tree { health: 100 })
+                .insert(Transform::from_translation(
+                    tile_to_world(IVec2::new(x, y)).extend(20.0),
+                ));
+        }
+    }
+    app
+}
+
+fn tree_at(app: &mut App, tile: IVec2) -> Entity {
+    let position = tile_to_world(tile);
+    app.world
+        .query::<(Entity, &Transform, &Tree)>()
+        .iter(&app.world)
+        .find(|(_, transform, _)| transform.translation.truncate() == position)
+        .unwrap()
+        .0
+}
+
+// the burning trees and the stumps, in a comparable form
+fn fire_state(app: &mut App) -> (Vec<(i32, i32, i16)>, usize) {
+    let mut burning: Vec<(i32, i32, i16)> = app
+        .world
+        .query_filtered::<(&Transform, &Tree), With<Burning>>()
+        .iter(&app.world)
+        .map(|(transform, tree)| {
+            (
+                transform.translation.x as i32,
+                transform.translation.y as i32,
+                tree.health,
+            )
+        })
+        .collect();
+    burning.sort_unstable();
+    let stumps = app.world.query::<&CharredStump>().iter(&app.world).count();
+    (burning, stumps)
+}
+
+#[test]
+fn fire_spreads_the_same_way_with_the_same_seed() {
+    let mut states = Vec::new();
+    for _ in 0..2 {
+        let mut app = new_game_with_forest(7);
+        let center = tree_at(&mut app, IVec2::new(4, 4));
+        app.world
+            .resource_mut::<Events<IgniteTree>>()
+            .send(IgniteTree(center));
+        // 8 seconds of fire steps
+        run_frames(&mut app, 8 * 60);
+        states.push(fire_state(&mut app));
+    }
+
+    let (burning, _) = &states[0];
+    assert!(burning.len() > 1, "the fire did not spread");
+    assert_eq!(states[0], states[1]);
+}
+
+#[test]
+fn burned_tree_leaves_a_charred_stump() {
+    let mut app = new_game_with_forest(1);
+    let tree = tree_at(&mut app, IVec2::new(2, 2));
+    app.world
+        .resource_mut::<Events<IgniteTree>>()
+        .send(IgniteTree(tree));
+
+    // 100 health, 10 damage per second
+    run_frames(&mut app, 11 * 60);
+
+    assert!(app.world.get_entity(tree).is_none());
+    assert!(fire_state(&mut app).1 >= 1);
+}
+
+#[test]
+fn tree_hit_on_the_tick_it_burns_down() {
+    let mut app = new_game_with_forest(1);
+    let tree = tree_at(&mut app, IVec2::new(2, 2));
+    app.world.get_mut::<Tree>(tree).unwrap().health = 10;
+    app.world
+        .resource_mut::<Events<IgniteTree>>()
+        .send(IgniteTree(tree));
+
+    // hit on every tick, one of them is the fire step despawning the tree
+    for _ in 0..2 * 60 {
+        app.world
+            .resource_mut::<Events<TreeHit>>()
+            .send(TreeHit(tree));
+        app.update();
+    }
+    assert!(app.world.get_entity(tree).is_none());
+}
+
+#[test]
+fn player_extinguishes_a_burning_tree() {
+    let mut app = new_game_with_forest(1);
+    // nothing else to interact with around the player
+    let posts: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<TradingPost>>()
+        .iter(&app.world)
+        .collect();
+    for post in posts {
+        app.world.despawn(post);
+    }
+    let tree = app
+        .world
+        .spawn()
+        .insert(Tree { health: 100 })
+        .insert(Transform::from_xyz(TILE_SIZE * SCALE * 0.5, 0.0, 20.0))
+        .id();
+    app.world
+        .resource_mut::<Events<IgniteTree>>()
+        .send(IgniteTree(tree));
+    run_frames(&mut app, 2);
+    assert!(app.world.get::<Burning>(tree).is_some());
+
+    press_keys(&mut app, &[KeyCode::E], ElementState::Pressed);
+    run_frames(&mut app, 2);
+
+    assert!(app.world.get::<Burning>(tree).is_none());
+}
@@ -0,0 +1,561 @@
+mod common;
+
+use bevy::{
+    ecs::{event::Events, system::CommandQueue},
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButton, MouseButtonInput},
+        ElementState,
+    },
+    prelude::*,
+};
+use bevy_game::{
+    clock::GameClock,
+    depth::YSorted,
+    interaction::NearestInteractable,
+    loading::{GameAssets, GameData},
+    map::{Map, Terrain, TILE_SIZE},
+    movement::Velocity,
+    player::{Direction, Player, PlayerInput, PlayerState},
+    popup::{trigger_popup, Popup},
+    resource_counter::{CoinResource, ResourceCounter, WoodResource},
+    stamina::Stamina,
+    tick::Interpolated,
+    trading_post::TradingPost,
+    tree_feedback::FallingTree,
+    trees::{Tree, TREE_AMOUNT},
+    weather::{FallenLog, Weather, WeatherState},
+    SCALE,
+};
+use common::{new_game, new_game_with, press_keys, remove_trees};
+use std::time::Duration;
+
+fn player_position(app: &mut App) -> Vec3 {
+    app.world
+        .query_filtered::<&Transform, With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap()
+        .translation
+}
+
+fn wood_count(app: &mut App) -> u32 {
+    app.world
+        .query_filtered::<&ResourceCounter, With<WoodResource>>()
+        .iter(&app.world)
+        .next()
+        .unwrap()
+        .0
+}
+
+fn coin_count(app: &mut App) -> u32 {
+    app.world
+        .query_filtered::<&ResourceCounter, With<CoinResource>>()
+        .iter(&app.world)
+        .next()
+        .unwrap()
+        .0
+}
+
+#[test]
+fn game_starts_with_player_and_trees() {
+    let mut app = new_game();
+
+    assert_eq!(player_position(&mut app).truncate(), Vec2::ZERO);
+    assert_eq!(
+        app.world.query::<&Tree>().iter(&app.world).count(),
+        TREE_AMOUNT
+    );
+    assert_eq!(wood_count(&mut app), 0);
+}
+
+fn player_velocity(app: &mut App) -> Vec2 {
+    app.world
+        .query_filtered::<&Velocity, With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap()
+        .0
+}
+
+#[test]
+fn player_moves_at_a_fixed_rate() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    press_keys(&mut app, &[KeyCode::D], ElementState::Pressed);
+    // one second of ticks, at 60 Hz
+    for _ in 0..60 {
+        app.update();
+    }
+
+    // the player speed is 3 tiles per second, reached after a short acceleration
+    let speed = 3.0 * TILE_SIZE * SCALE;
+    assert_eq!(player_velocity(&mut app), Vec2::new(speed, 0.0));
+    let x = player_position(&mut app).x;
+    assert!(x < speed && x > speed * 0.9, "x = {}", x);
+
+    // the friction stops the player quickly
+    press_keys(&mut app, &[KeyCode::D], ElementState::Released);
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(player_velocity(&mut app), Vec2::ZERO);
+}
+
+#[test]
+fn diagonal_movement_is_not_faster() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    press_keys(&mut app, &[KeyCode::D, KeyCode::S], ElementState::Pressed);
+    for _ in 0..30 {
+        app.update();
+    }
+
+    let velocity = player_velocity(&mut app);
+    assert!((velocity.length() - 3.0 * TILE_SIZE * SCALE).abs() < 0.01);
+    assert!((velocity.x + velocity.y).abs() < 0.01);
+}
+
+#[test]
+fn chopping_a_tree_gives_wood() {
+    let mut app = new_game();
+
+    // replace the randomly placed trees by a single one, right next to the player
+    remove_trees(&mut app);
+    let tree = app
+        .world
+        .spawn()
+        .insert(Tree { health: 100 })
+        .insert(Transform::from_xyz(TILE_SIZE * SCALE * 0.5, 0.0, 20.0))
+        .id();
+
+    app.world
+        .resource_mut::<Events<MouseButtonInput>>()
+        .send(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ElementState::Pressed,
+        });
+    app.update();
+
+    assert_eq!(wood_count(&mut app), 1);
+    assert_eq!(app.world.get::<Tree>(tree).unwrap().health, 60);
+}
+
+#[test]
+fn interacting_with_a_trading_post_sells_wood() {
+    let mut app = new_game();
+
+    // the player starts right on the trading post at the center of the map
+    let post = app
+        .world
+        .query_filtered::<(Entity, &Transform), With<TradingPost>>()
+        .iter(&app.world)
+        .find(|(_, transform)| transform.translation.truncate() == Vec2::ZERO)
+        .unwrap()
+        .0;
+    assert_eq!(app.world.resource::<NearestInteractable>().0, Some(post));
+
+    app.world
+        .query_filtered::<&mut ResourceCounter, With<WoodResource>>()
+        .iter_mut(&mut app.world)
+        .next()
+        .unwrap()
+        .0 = 5;
+    app.world
+        .resource_mut::<Events<KeyboardInput>>()
+        .send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::E),
+            state: ElementState::Pressed,
+        });
+    app.update();
+
+    assert_eq!(wood_count(&mut app), 0);
+    assert_eq!(coin_count(&mut app), 15);
+    let offer = &app.world.get::<TradingPost>(post).unwrap().offers[0];
+    assert_eq!(offer.stock, offer.max_stock - 5);
+}
+
+#[test]
+fn sprinting_is_faster_and_uses_stamina() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    press_keys(
+        &mut app,
+        &[KeyCode::D, KeyCode::LShift],
+        ElementState::Pressed,
+    );
+    for _ in 0..60 {
+        app.update();
+    }
+
+    // one second of sprinting, 1.6 times the speed for 20 stamina
+    let speed = 1.6 * 3.0 * TILE_SIZE * SCALE;
+    assert!((player_velocity(&mut app).x - speed).abs() < 0.01);
+    let x = player_position(&mut app).x;
+    assert!(x < speed && x > speed * 0.9, "x = {}", x);
+    let stamina = app
+        .world
+        .query_filtered::<&Stamina, With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap()
+        .current();
+    assert!((stamina - 80.0).abs() < 0.01, "stamina = {}", stamina);
+}
+
+#[test]
+fn trading_posts_restock_at_dawn() {
+    let mut app = new_game_with(|app| {
+        app.insert_resource(GameClock::new(Duration::from_secs(1)));
+    });
+    for mut post in app
+        .world
+        .query::<&mut TradingPost>()
+        .iter_mut(&mut app.world)
+    {
+        post.offers[0].stock = 0;
+    }
+
+    // a whole day of one second, at 60 Hz
+    for _ in 0..60 {
+        app.update();
+    }
+
+    assert_eq!(app.world.resource::<GameClock>().day(), 2);
+    for post in app.world.query::<&TradingPost>().iter(&app.world) {
+        assert_eq!(post.offers[0].stock, post.offers[0].max_stock);
+    }
+}
+
+#[test]
+fn felled_tree_falls_before_disappearing() {
+    let mut app = new_game();
+
+    remove_trees(&mut app);
+    let tree = app
+        .world
+        .spawn()
+        .insert(Tree { health: 40 })
+        .insert(Transform::from_xyz(TILE_SIZE * SCALE * 0.5, 0.0, 20.0))
+        .id();
+
+    app.world
+        .resource_mut::<Events<MouseButtonInput>>()
+        .send(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ElementState::Pressed,
+        });
+    app.update();
+
+    assert!(app.world.get::<Tree>(tree).is_none());
+    assert!(app.world.get::<FallingTree>(tree).is_some());
+
+    // the hit shake stops, the tree leans more and more until it lies on the ground
+    let mut angle = 0.0;
+    for _ in 0..30 {
+        app.update();
+        let (_, new_angle) = app
+            .world
+            .get::<Transform>(tree)
+            .unwrap()
+            .rotation
+            .to_axis_angle();
+        assert!(new_angle >= angle, "{} after {}", new_angle, angle);
+        angle = new_angle;
+    }
+
+    // the fall and the time on the ground last less than 1.5 second
+    for _ in 0..60 {
+        app.update();
+    }
+    assert!(app.world.get_entity(tree).is_none());
+}
+
+#[test]
+fn storm_does_not_knock_down_a_tree_felled_on_the_same_tick() {
+    let mut app = new_game();
+
+    remove_trees(&mut app);
+    let tree = app
+        .world
+        .spawn()
+        .insert(Tree { health: 40 })
+        .insert(Transform::from_xyz(TILE_SIZE * SCALE * 0.5, 0.0, 20.0))
+        .id();
+
+    // a storm knocking down a tree on every tick, from the tick the tree is felled
+    let mut weather = app.world.resource_mut::<Weather>();
+    weather.state = WeatherState::Storm;
+    weather.knockdown_chance = 1000.0;
+    app.world
+        .resource_mut::<Events<MouseButtonInput>>()
+        .send(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ElementState::Pressed,
+        });
+    app.update();
+
+    assert!(app.world.get::<FallingTree>(tree).is_some());
+    let logs = app
+        .world
+        .query_filtered::<Entity, With<FallenLog>>()
+        .iter(&app.world)
+        .count();
+    assert_eq!(logs, 0);
+}
+
+#[test]
+fn popups_at_the_same_place_are_stacked() {
+    let mut app = new_game();
+
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    let game_assets = app.world.resource::<GameAssets>();
+    for text in ["+1", "+2"] {
+        trigger_popup(&mut commands, game_assets, Vec3::ZERO, Popup::text(text));
+    }
+    queue.apply(&mut app.world);
+
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let mut heights: Vec<f32> = app
+        .world
+        .query::<(&Text, &Parent)>()
+        .iter(&app.world)
+        .filter(|(text, _)| text.sections[0].value.starts_with('+'))
+        .map(|(_, parent)| parent.0)
+        .collect::<Vec<Entity>>()
+        .into_iter()
+        .map(|popup| app.world.get::<Transform>(popup).unwrap().translation.y)
+        .collect();
+    heights.sort_by(f32::total_cmp);
+
+    assert_eq!(heights.len(), 2);
+    assert!(heights[1] - heights[0] > 10.0);
+}
+
+#[test]
+fn chopping_hits_only_the_closest_tree_in_front() {
+    let mut app = new_game();
+
+    // the player faces right at the start
+    remove_trees(&mut app);
+    let mut spawn_tree = |x: f32| {
+        app.world
+            .spawn()
+            .insert(Tree { health: 100 })
+            .insert(Transform::from_xyz(x, 0.0, 20.0))
+            .id()
+    };
+    let behind = spawn_tree(-TILE_SIZE * SCALE * 0.5);
+    let front = spawn_tree(TILE_SIZE * SCALE * 0.5);
+    let further = spawn_tree(TILE_SIZE * SCALE * 0.7);
+
+    app.world
+        .resource_mut::<Events<MouseButtonInput>>()
+        .send(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ElementState::Pressed,
+        });
+    app.update();
+
+    assert_eq!(wood_count(&mut app), 1);
+    assert_eq!(app.world.get::<Tree>(front).unwrap().health, 60);
+    assert_eq!(app.world.get::<Tree>(behind).unwrap().health, 100);
+    assert_eq!(app.world.get::<Tree>(further).unwrap().health, 100);
+}
+
+#[test]
+fn player_keeps_facing_down_and_chops_below() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    app.world
+        .resource_mut::<Events<KeyboardInput>>()
+        .send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::S),
+            state: ElementState::Pressed,
+        });
+    for _ in 0..10 {
+        app.update();
+    }
+    app.world
+        .resource_mut::<Events<KeyboardInput>>()
+        .send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::S),
+            state: ElementState::Released,
+        });
+    app.update();
+
+    let (state, direction) = app
+        .world
+        .query_filtered::<(&PlayerState, &Direction), With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap();
+    assert!(matches!(state, PlayerState::Stand(Direction::Down)));
+    assert!(matches!(direction, Direction::Down));
+
+    let below = player_position(&mut app) - Vec3::new(0.0, 12.0 * SCALE, 0.0);
+    let tree = app
+        .world
+        .spawn()
+        .insert(Tree { health: 100 })
+        .insert(Transform::from_translation(below))
+        .id();
+    app.world
+        .resource_mut::<Events<MouseButtonInput>>()
+        .send(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ElementState::Pressed,
+        });
+    app.update();
+
+    assert_eq!(app.world.get::<Tree>(tree).unwrap().health, 60);
+}
+
+#[test]
+fn water_blocks_the_player_and_trees_grow_on_grass() {
+    let mut app = new_game();
+
+    let map = app.world.resource::<Map>();
+    assert_eq!(map.terrain_at(IVec2::new(-6, 2)), Terrain::Water);
+    assert_eq!(map.terrain_at(IVec2::new(0, -3)), Terrain::Path);
+    let tree_tiles: Vec<Vec2> = app
+        .world
+        .query_filtered::<&Transform, With<Tree>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    let map = app.world.resource::<Map>();
+    for position in tree_tiles {
+        assert_eq!(map.terrain_at_world(position), Terrain::Grass);
+    }
+
+    // walk west from the beach, toward the pond
+    remove_trees(&mut app);
+    let start = Vec3::new(-4.0 * TILE_SIZE * SCALE, 2.0 * TILE_SIZE * SCALE, 10.0);
+    let player = app
+        .world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap();
+    app.world
+        .entity_mut(player)
+        .insert(Transform::from_translation(start).with_scale(Vec3::splat(SCALE)))
+        .insert(Interpolated::new(start));
+    press_keys(&mut app, &[KeyCode::Q], ElementState::Pressed);
+    for _ in 0..60 {
+        app.update();
+    }
+
+    let x = player_position(&mut app).x;
+    assert!(x > -4.5 * TILE_SIZE * SCALE, "x = {}", x);
+    assert!(x < start.x, "x = {}", x);
+}
+
+#[test]
+fn sprites_lower_on_screen_are_drawn_in_front() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    let mut spawn_tree = |y: f32| {
+        app.world
+            .spawn()
+            .insert(Tree { health: 100 })
+            .insert(YSorted { anchor: -11.0 })
+            .insert(Transform::from_xyz(0.0, y, 0.0).with_scale(Vec3::splat(SCALE)))
+            .id()
+    };
+    let above = spawn_tree(TILE_SIZE * SCALE * 2.0);
+    let below = spawn_tree(-TILE_SIZE * SCALE * 2.0);
+    app.update();
+
+    let player_z = player_position(&mut app).z;
+    assert!(app.world.get::<Transform>(above).unwrap().translation.z < player_z);
+    assert!(app.world.get::<Transform>(below).unwrap().translation.z > player_z);
+}
+
+#[test]
+fn clicking_a_tile_walks_around_the_trees() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    // a row of trees between the player and the destination
+    for y in -2..=2 {
+        app.world
+            .spawn()
+            .insert(Tree { health: 100 })
+            .insert(Transform::from_xyz(
+                2.0 * TILE_SIZE * SCALE,
+                y as f32 * TILE_SIZE * SCALE,
+                0.0,
+            ));
+    }
+    app.world.resource_mut::<PlayerInput>().move_to = Some(IVec2::new(4, 0));
+    for _ in 0..300 {
+        app.update();
+    }
+
+    let position = player_position(&mut app).truncate();
+    assert!(
+        position.distance(Vec2::new(4.0 * TILE_SIZE * SCALE, 0.0)) < 4.0,
+        "position = {}",
+        position
+    );
+}
+
+#[test]
+fn clicking_a_tree_walks_to_it_and_chops_it() {
+    let mut app = new_game();
+    remove_trees(&mut app);
+
+    let tree = app
+        .world
+        .spawn()
+        .insert(Tree { health: 100 })
+        .insert(Transform::from_xyz(
+            -3.0 * TILE_SIZE * SCALE,
+            -2.0 * TILE_SIZE * SCALE,
+            0.0,
+        ))
+        .id();
+    app.world.resource_mut::<PlayerInput>().move_to = Some(IVec2::new(-3, -2));
+    for _ in 0..300 {
+        app.update();
+    }
+
+    // 40 damage per hit, the tree fell after 3 hits
+    assert!(app.world.get::<Tree>(tree).is_none());
+    assert_eq!(wood_count(&mut app), 3);
+}
+
+#[test]
+fn invalid_data_files_are_listed() {
+    let errors = GameData::parse(|path| match path {
+        "quests.txt" => Ok("fell 3".to_string()),
+        "merchant.txt" => Ok("week 7".to_string()),
+        "map.txt" => Ok("row ..~~..".to_string()),
+        _ => Err("not found".to_string()),
+    })
+    .err()
+    .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "quests.txt: invalid quest line: 'fell 3'".to_string(),
+            "map.txt: invalid map line: 'row ..~~..'".to_string(),
+            "dialogues/forester.txt: not found".to_string(),
+            "dialogues/notice_board.txt: not found".to_string(),
+        ]
+    );
+}
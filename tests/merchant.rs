@@ -0,0 +1,138 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_game::{
+    clock::GameClock,
+    headless::headless_app,
+    map::{tile_to_world, MapLayout},
+    merchant::{Deal, Merchant, MerchantCatalog, OpenTrade},
+    player::{Direction, PlayerInput},
+    resource_counter::{CoinResource, Item, SaplingResource, WoodResource},
+    trading_post::TradeOffer,
+    trees::{Sapling, Tree},
+};
+use common::{counter, move_player, new_game_with, player, remove_trees};
+use std::time::Duration;
+
+const CATALOG: &str = "
+# a visit on the first day
+visit 1
+greeting Fine wares!
+buys wood 2.0 5
+sells sapling 10 2
+";
+
+// the greeting of the merchant in town, and the stocks of its deals
+fn merchant(app: &mut App) -> Option<(String, Vec<u32>)> {
+    app.world
+        .query::<&Merchant>()
+        .iter(&app.world)
+        .next()
+        .map(|merchant| {
+            let stocks = merchant.deals.iter().map(Deal::stock).collect();
+            (merchant.greeting.clone(), stocks)
+        })
+}
+
+fn choose(app: &mut App, choice: u8) {
+    app.world.resource_mut::<PlayerInput>().choice = Some(choice);
+    app.update();
+}
+
+#[test]
+fn catalog_visits_come_back_every_week() {
+    let app = headless_app();
+    let catalog = app.world.resource::<MerchantCatalog>();
+    assert!(catalog.visit_on(1).is_none());
+    let visit = catalog.visit_on(3).unwrap();
+    assert_eq!(visit.day, 3);
+    assert_eq!(catalog.visit_on(10).unwrap().day, 3);
+    assert_eq!(catalog.visit_on(13).unwrap().day, 6);
+
+    assert!(MerchantCatalog::parse("visit 1\nbuys wood lots 5").is_err());
+    assert!(MerchantCatalog::parse("sells sapling 10 2").is_err());
+}
+
+#[test]
+fn player_trades_with_the_merchant_and_plants_a_sapling() {
+    let mut app = new_game_with(|app| {
+        app.insert_resource(MerchantCatalog::parse(CATALOG).unwrap());
+    });
+    assert_eq!(merchant(&mut app).unwrap().0, "Fine wares!");
+
+    let merchant_tile = app.world.resource::<MapLayout>().merchant;
+    move_player(&mut app, merchant_tile);
+    app.world.resource_mut::<PlayerInput>().interact = true;
+    app.update();
+    assert!(app.world.resource::<OpenTrade>().0.is_some());
+
+    // the merchant only buys as much wood as it has stock for
+    counter::<WoodResource>(&mut app).0 = 8;
+    let season = app.world.resource::<GameClock>().season();
+    let price = TradeOffer::new(Item::Wood, 2.0, 5).price(season);
+    choose(&mut app, 0);
+    assert_eq!(counter::<WoodResource>(&mut app).0, 3);
+    assert_eq!(counter::<CoinResource>(&mut app).0, 5 * price);
+
+    // then it sells its saplings, while the player has the coins
+    counter::<CoinResource>(&mut app).0 = 25;
+    for _ in 0..3 {
+        choose(&mut app, 1);
+    }
+    assert_eq!(counter::<SaplingResource>(&mut app).0, 2);
+    assert_eq!(counter::<CoinResource>(&mut app).0, 5);
+    assert_eq!(merchant(&mut app).unwrap().1, vec![0, 0]);
+
+    // walking away closes the window
+    let tile = IVec2::new(-4, -3);
+    move_player(&mut app, tile);
+    assert!(app.world.resource::<OpenTrade>().0.is_none());
+
+    // the sapling is planted on the tile the player faces
+    let player = player(&mut app);
+    app.world.entity_mut(player).insert(Direction::Right);
+    remove_trees(&mut app);
+    app.world.resource_mut::<PlayerInput>().plant = true;
+    app.update();
+    app.update();
+    assert_eq!(counter::<SaplingResource>(&mut app).0, 1);
+    let planted: Vec<Vec3> = app
+        .world
+        .query_filtered::<&Transform, (With<Tree>, With<Sapling>)>()
+        .iter(&app.world)
+        .map(|transform| transform.translation)
+        .collect();
+    assert_eq!(planted.len(), 1);
+    assert_eq!(
+        planted[0].truncate(),
+        tile_to_world(tile + IVec2::new(1, 0))
+    );
+
+    // a tree can't be planted on another one
+    app.world.resource_mut::<PlayerInput>().plant = true;
+    app.update();
+    app.update();
+    assert_eq!(counter::<SaplingResource>(&mut app).0, 1);
+}
+
+#[test]
+fn merchant_leaves_at_dusk() {
+    let mut app = new_game_with(|app| {
+        app.insert_resource(MerchantCatalog::parse(CATALOG).unwrap())
+            .insert_resource(GameClock::new(Duration::from_secs(10)));
+    });
+    assert!(merchant(&mut app).is_some());
+
+    let merchant_tile = app.world.resource::<MapLayout>().merchant;
+    move_player(&mut app, merchant_tile);
+    app.world.resource_mut::<PlayerInput>().interact = true;
+    app.update();
+    assert!(app.world.resource::<OpenTrade>().0.is_some());
+
+    // the day is 10 seconds long, dusk comes after 4.5 seconds
+    for _ in 0..5 * 60 {
+        app.update();
+    }
+    assert!(merchant(&mut app).is_none());
+    assert!(app.world.resource::<OpenTrade>().0.is_none());
+}
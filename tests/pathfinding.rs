@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use bevy_game::pathfinding::find_path;
+
+// a wall on x = 0 from y = -2 to y = 2
+fn is_walkable(tile: IVec2) -> bool {
+    tile.x.abs() <= 5 && tile.y.abs() <= 5 && !(tile.x == 0 && tile.y.abs() <= 2)
+}
+
+#[test]
+fn path_goes_around_obstacles() {
+    let path = find_path(IVec2::new(-2, 0), IVec2::new(2, 0), is_walkable).unwrap();
+
+    assert_eq!(*path.last().unwrap(), IVec2::new(2, 0));
+    assert!(path.iter().all(|tile| is_walkable(*tile)));
+    // each step goes to a neighbor tile
+    let mut previous = IVec2::new(-2, 0);
+    for tile in path.iter() {
+        let step = (*tile - previous).abs();
+        assert!(step.x <= 1 && step.y <= 1);
+        previous = *tile;
+    }
+    // around the end of the wall, without cutting its corners
+    assert_eq!(path.len(), 8);
+
+    // the same path is always found
+    assert_eq!(
+        find_path(IVec2::new(-2, 0), IVec2::new(2, 0), is_walkable),
+        Some(path)
+    );
+}
+
+#[test]
+fn no_path_to_an_obstacle_or_an_enclosed_tile() {
+    assert_eq!(find_path(IVec2::ZERO, IVec2::new(0, 1), is_walkable), None);
+    assert_eq!(
+        find_path(IVec2::ZERO, IVec2::new(9, 0), |tile| tile.x < 5
+            || tile == IVec2::new(9, 0)),
+        None
+    );
+    assert_eq!(
+        find_path(IVec2::new(3, 3), IVec2::new(3, 3), is_walkable),
+        Some(vec![])
+    );
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_game::{
+    clock::GameClock,
+    dialogue::{Carried, OpenDialogue},
+    headless::headless_app,
+    map::MapLayout,
+    player::PlayerInput,
+    quests::{Objective, QuestCatalog, QuestLog, QuestStatus, StartQuest},
+    resource_counter::{CoinResource, Item, WoodResource},
+};
+use common::{counter, move_player, new_game, new_game_with};
+use std::time::Duration;
+
+fn start_quest(app: &mut App, name: &str) {
+    app.world
+        .resource_mut::<Events<StartQuest>>()
+        .send(StartQuest(name.to_string()));
+    app.update();
+}
+
+fn quest_status(app: &App, name: &str) -> Option<QuestStatus> {
+    app.world
+        .resource::<QuestLog>()
+        .get(name)
+        .map(|quest| quest.status())
+}
+
+#[test]
+fn quest_catalog_is_parsed() {
+    let app = headless_app();
+    let catalog = app.world.resource::<QuestCatalog>();
+    let firewood = catalog.get("firewood").unwrap();
+    assert_eq!(firewood.title, "Firewood for the capital");
+    assert_eq!(firewood.days, Some(2));
+    assert_eq!(firewood.objectives, vec![Objective::Sell(Item::Wood, 30)]);
+    assert_eq!(firewood.rewards, vec![(Carried::Coins, 60)]);
+    assert!(catalog.get("dragon").is_none());
+
+    assert!(QuestCatalog::parse("fell 3").is_err());
+    assert!(QuestCatalog::parse("quest logs\ncollect gold 3").is_err());
+    assert!(QuestCatalog::parse("quest logs\nreward coins lots").is_err());
+    // the names are words of the save file
+    assert!(QuestCatalog::parse("quest -").is_err());
+    assert!(QuestCatalog::parse("quest Logs").is_err());
+}
+
+#[test]
+fn selling_wood_completes_the_contract() {
+    let mut app = new_game();
+    start_quest(&mut app, "firewood");
+    assert_eq!(quest_status(&app, "firewood"), Some(QuestStatus::Active));
+    let clock = app.world.resource::<GameClock>();
+    let deadline = clock.elapsed() + clock.day_length() * 2;
+    assert!(app
+        .world
+        .resource::<QuestLog>()
+        .get("firewood")
+        .unwrap()
+        .deadline()
+        .map_or(false, |due| due.as_millis().abs_diff(deadline.as_millis())
+            < 100));
+
+    // the center trading post buys up to 30 logs, only the sold ones count
+    let post = &app.world.resource::<MapLayout>().trading_posts[0];
+    let (tile, offer) = (post.tile, post.offers[0].clone());
+    let price = offer.price(app.world.resource::<GameClock>().season());
+    move_player(&mut app, tile);
+    counter::<WoodResource>(&mut app).0 = 20;
+    app.world.resource_mut::<PlayerInput>().interact = true;
+    app.update();
+    let quest_log = app.world.resource::<QuestLog>();
+    let quest = quest_log.get("firewood").unwrap();
+    assert_eq!(quest.status(), QuestStatus::Active);
+    assert_eq!(
+        quest.objectives().collect::<Vec<_>>(),
+        vec![(Objective::Sell(Item::Wood, 30), 20)]
+    );
+    assert_eq!(counter::<CoinResource>(&mut app).0, 20 * price);
+
+    // the merchant is not in town, so the other logs are sold at the post too
+    counter::<WoodResource>(&mut app).0 = 15;
+    app.world.resource_mut::<PlayerInput>().interact = true;
+    app.update();
+    app.update();
+    assert_eq!(quest_status(&app, "firewood"), Some(QuestStatus::Completed));
+    assert_eq!(counter::<WoodResource>(&mut app).0, 5);
+    assert_eq!(counter::<CoinResource>(&mut app).0, 30 * price + 60);
+
+    // an active quest can't be taken twice, a finished one can
+    start_quest(&mut app, "firewood");
+    assert_eq!(quest_status(&app, "firewood"), Some(QuestStatus::Active));
+    start_quest(&mut app, "firewood");
+    assert_eq!(app.world.resource::<QuestLog>().iter().count(), 1);
+}
+
+#[test]
+fn contract_fails_when_its_days_run_out() {
+    let mut app = new_game_with(|app| {
+        app.insert_resource(GameClock::new(Duration::from_secs(2)));
+    });
+
+    start_quest(&mut app, "clearing");
+    for _ in 0..60 {
+        app.update();
+    }
+    assert_eq!(quest_status(&app, "clearing"), Some(QuestStatus::Active));
+    for _ in 0..70 {
+        app.update();
+    }
+    assert_eq!(quest_status(&app, "clearing"), Some(QuestStatus::Failed));
+}
+
+#[test]
+fn contracts_are_taken_at_the_notice_board() {
+    let mut app = new_game();
+    let (board, _) = app.world.resource::<MapLayout>().signs[0].clone();
+    move_player(&mut app, board);
+
+    // interacting again and picking an answer skip the typing
+    for input in [None, None, Some(0), Some(0), Some(0), Some(0)] {
+        let mut player_input = app.world.resource_mut::<PlayerInput>();
+        match input {
+            None => player_input.interact = true,
+            Some(choice) => player_input.choice = Some(choice),
+        }
+        app.update();
+    }
+    assert_eq!(quest_status(&app, "firewood"), Some(QuestStatus::Active));
+
+    // the contract taken is not offered again
+    let dialogue = app.world.resource::<OpenDialogue>().0.clone().unwrap();
+    assert!(!dialogue.is_typing());
+    for choice in [0, 0, 0] {
+        app.world.resource_mut::<PlayerInput>().choice = Some(choice);
+        app.update();
+    }
+    assert_eq!(app.world.resource::<QuestLog>().iter().count(), 1);
+}
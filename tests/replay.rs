@@ -0,0 +1,89 @@
+mod common;
+
+use bevy::{
+    ecs::event::Events,
+    input::{
+        mouse::{MouseButton, MouseButtonInput},
+        ElementState,
+    },
+    prelude::*,
+};
+use bevy_game::{
+    game_state::GameRng,
+    player::PlayerInput,
+    replay::{run_replay, state_hash, verify_replay, InputRecorder, Replay, ReplayError},
+};
+use common::{new_game_with, press_keys, run_frames};
+
+fn send_click(app: &mut App, state: ElementState) {
+    app.world
+        .resource_mut::<Events<MouseButtonInput>>()
+        .send(MouseButtonInput {
+            button: MouseButton::Left,
+            state,
+        });
+}
+
+// play a short game with the keyboard and mouse, and return its recording
+fn record_game() -> Replay {
+    let mut app = new_game_with(|app| {
+        app.world.resource_mut::<GameRng>().next_seed = Some(42);
+        app.insert_resource(InputRecorder::new(
+            std::env::temp_dir().join("unused.replay"),
+        ));
+    });
+
+    press_keys(&mut app, &[KeyCode::D], ElementState::Pressed);
+    run_frames(&mut app, 40);
+    press_keys(&mut app, &[KeyCode::D], ElementState::Released);
+    press_keys(&mut app, &[KeyCode::Z], ElementState::Pressed);
+    run_frames(&mut app, 25);
+    press_keys(&mut app, &[KeyCode::Z], ElementState::Released);
+    for _ in 0..5 {
+        send_click(&mut app, ElementState::Pressed);
+        run_frames(&mut app, 1);
+        send_click(&mut app, ElementState::Released);
+        run_frames(&mut app, 30);
+    }
+    app.world.resource_mut::<PlayerInput>().move_to = Some(IVec2::new(-2, -1));
+    run_frames(&mut app, 60);
+    press_keys(&mut app, &[KeyCode::Key2], ElementState::Pressed);
+    run_frames(&mut app, 1);
+    press_keys(&mut app, &[KeyCode::Key2], ElementState::Released);
+    run_frames(&mut app, 5);
+
+    let mut replay = app.world.resource::<InputRecorder>().replay().clone();
+    replay.hash = Some(state_hash(&mut app.world));
+    replay
+}
+
+#[test]
+fn replay_matches_the_recorded_game() {
+    let replay = record_game();
+
+    assert_eq!(replay.seed, 42);
+    assert!(verify_replay(&replay).is_ok());
+}
+
+#[test]
+fn replay_survives_the_file_format() {
+    let replay = record_game();
+    let parsed = Replay::parse(&replay.to_string()).unwrap();
+
+    assert_eq!(parsed.seed, replay.seed);
+    assert_eq!(parsed.hash, replay.hash);
+    assert!(parsed.inputs == replay.inputs);
+    assert!(verify_replay(&parsed).is_ok());
+}
+
+#[test]
+fn replay_with_other_input_does_not_match() {
+    let mut replay = record_game();
+    replay.inputs.truncate(10);
+
+    assert!(matches!(
+        verify_replay(&replay),
+        Err(ReplayError::Mismatch { .. })
+    ));
+    assert_eq!(run_replay(&replay), run_replay(&replay));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_game::{
+    clock::GameClock,
+    dialogue::DialogueFlags,
+    game_state::AppState,
+    quests::{QuestLog, QuestProgress, QuestStatus, StartQuest},
+    resource_counter::Item,
+    save::{SaveFile, SaveGame, SavedQuest},
+};
+use common::{new_game, new_game_with, run_frames};
+use std::{env, fs, process, time::Duration};
+
+const SAVE: &str = "
+flag met_forester
+quest firewood active 60000 20
+quest clearing failed 0 2
+quest dragon completed - 1
+";
+
+#[test]
+fn save_file_is_parsed() {
+    let save = SaveGame::parse(SAVE).unwrap();
+    assert_eq!(save.flags, vec!["met_forester".to_string()]);
+    assert_eq!(
+        save.quests[0],
+        SavedQuest {
+            name: "firewood".to_string(),
+            status: QuestStatus::Active,
+            time_left: Some(Duration::from_secs(60)),
+            progress: vec![20],
+        }
+    );
+    assert_eq!(save.quests[2].time_left, None);
+    assert_eq!(SaveGame::parse(&save.to_string()).unwrap(), save);
+
+    assert!(SaveGame::parse("quest firewood done 0 20").is_err());
+    assert!(SaveGame::parse("quest firewood active soon 20").is_err());
+    assert!(SaveGame::parse("flag").is_err());
+}
+
+#[test]
+fn save_of_a_game_is_read_back() {
+    let mut app = new_game();
+    app.world
+        .resource_mut::<DialogueFlags>()
+        .set("took_firewood");
+    app.world.resource_mut::<DialogueFlags>().set("visit_2");
+    app.world
+        .resource_mut::<Events<StartQuest>>()
+        .send(StartQuest("firewood".to_string()));
+    app.update();
+    app.world
+        .resource_mut::<Events<QuestProgress>>()
+        .send(QuestProgress::ItemSold(Item::Wood, 7));
+    // the deadline is not on a whole millisecond after a few ticks
+    run_frames(&mut app, 5);
+
+    let save = SaveGame::new(
+        app.world.resource::<GameClock>(),
+        app.world.resource::<DialogueFlags>(),
+        app.world.resource::<QuestLog>(),
+    );
+    assert_eq!(save.quests[0].progress, vec![7]);
+    assert!(save.quests[0].time_left.is_some());
+    assert_eq!(SaveGame::parse(&save.to_string()).unwrap(), save);
+}
+
+#[test]
+fn contracts_and_flags_are_kept_between_games() {
+    let path = env::temp_dir().join(format!("bevy_game_save_{}.txt", process::id()));
+    fs::write(&path, SAVE).unwrap();
+
+    // the quest unknown to the catalog is dropped
+    let mut app = new_game_with(|app| {
+        app.insert_resource(SaveFile::new(&path));
+    });
+    assert!(app
+        .world
+        .resource::<DialogueFlags>()
+        .contains("met_forester"));
+    let quest_log = app.world.resource::<QuestLog>();
+    assert_eq!(quest_log.iter().count(), 2);
+    let firewood = quest_log.get("firewood").unwrap();
+    assert_eq!(firewood.status(), QuestStatus::Active);
+    assert_eq!(firewood.objectives().next().unwrap().1, 20);
+    let clock = app.world.resource::<GameClock>();
+    let deadline = clock.elapsed() + Duration::from_secs(60);
+    assert!(firewood
+        .deadline()
+        .map_or(false, |due| due.as_millis().abs_diff(deadline.as_millis())
+            < 100));
+    assert_eq!(
+        quest_log.get("clearing").unwrap().status(),
+        QuestStatus::Failed
+    );
+
+    // the game is saved when it ends
+    app.world
+        .resource_mut::<Events<QuestProgress>>()
+        .send(QuestProgress::ItemSold(Item::Wood, 5));
+    app.world
+        .resource_mut::<Events<StartQuest>>()
+        .send(StartQuest("charcoal".to_string()));
+    app.update();
+    app.world
+        .resource_mut::<State<AppState>>()
+        .set(AppState::GameOver)
+        .unwrap();
+    app.update();
+
+    let save = SaveGame::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(save.flags, vec!["met_forester".to_string()]);
+    let quests: Vec<(&str, QuestStatus, &[u32])> = save
+        .quests
+        .iter()
+        .map(|quest| (quest.name.as_str(), quest.status, &quest.progress[..]))
+        .collect();
+    assert_eq!(
+        quests,
+        vec![
+            ("firewood", QuestStatus::Active, &[25][..]),
+            ("clearing", QuestStatus::Failed, &[2][..]),
+            ("charcoal", QuestStatus::Active, &[0][..]),
+        ]
+    );
+    let time_left = save.quests[0].time_left.unwrap();
+    assert!(time_left < Duration::from_secs(60) && time_left > Duration::from_secs(59));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use bevy::{ecs::system::CommandQueue, prelude::*};
+use bevy_game::{
+    map::{tile_to_world, TILE_SIZE},
+    player::Player,
+    texture_atlas::AtlasHandle,
+    tick::Interpolated,
+    trees::Tree,
+    wildlife::{spawn_animal, Animal, AnimalKind, AnimalState},
+    SCALE,
+};
+use common::{new_game_with_seed, remove_trees};
+
+fn animal_count(app: &mut App, kind: AnimalKind) -> usize {
+    app.world
+        .query::<&Animal>()
+        .iter(&app.world)
+        .filter(|animal| animal.kind() == kind)
+        .count()
+}
+
+#[test]
+fn animals_come_with_the_forest_and_leave_without_it() {
+    let mut app = new_game_with_seed(3);
+    assert_eq!(app.world.query::<&Animal>().iter(&app.world).count(), 0);
+
+    // the game starts in the morning, the birds live in the forest
+    for _ in 0..30 * 60 {
+        app.update();
+    }
+    let birds = animal_count(&mut app, AnimalKind::Bird);
+    assert!((1..=3).contains(&birds), "{} birds", birds);
+
+    // without trees, only the rabbits stay
+    remove_trees(&mut app);
+    for _ in 0..40 * 60 {
+        app.update();
+    }
+    assert_eq!(animal_count(&mut app, AnimalKind::Bird), 0);
+    assert_eq!(animal_count(&mut app, AnimalKind::Deer), 0);
+    assert!(animal_count(&mut app, AnimalKind::Rabbit) > 0);
+}
+
+#[test]
+fn animals_flee_from_the_player_and_are_blocked_by_trees() {
+    let mut app = new_game_with_seed(3);
+    remove_trees(&mut app);
+
+    // the player, a deer and a tree on the same line, the deer runs into the tree
+    let ground_y = -8.0 * SCALE;
+    let player = app
+        .world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(&app.world)
+        .next()
+        .unwrap();
+    let start = Vec3::new(0.0, ground_y, 0.0);
+    app.world
+        .entity_mut(player)
+        .insert(Transform::from_translation(start).with_scale(Vec3::splat(SCALE)))
+        .insert(Interpolated::new(start));
+    let tree = tile_to_world(IVec2::new(4, 0));
+    app.world
+        .spawn()
+        .insert(Tree { health: 100 })
+        .insert(Transform::from_translation(tree.extend(0.0)));
+
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    let deer_start = Vec2::new(2.5 * TILE_SIZE * SCALE, ground_y);
+    let deer = spawn_animal(
+        &mut commands,
+        app.world.resource::<AtlasHandle>(),
+        AnimalKind::Deer,
+        deer_start,
+    );
+    queue.apply(&mut app.world);
+
+    for _ in 0..60 {
+        app.update();
+    }
+    let animal = app.world.get::<Animal>(deer).unwrap();
+    assert_eq!(animal.state(), AnimalState::Flee);
+    let position = app.world.get::<Transform>(deer).unwrap().translation;
+    assert!(position.x > deer_start.x);
+    assert!(position.x < tree.x);
+    assert_eq!(position.y, ground_y);
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use bevy::{ecs::system::CommandQueue, prelude::*};
+use bevy_game::{
+    game_state::GameStats,
+    map::{tile_to_world, MapLayout},
+    player::PlayerInput,
+    resource_counter::{CoinResource, WoodResource},
+    texture_atlas::AtlasHandle,
+    trees::Tree,
+    workers::{spawn_worker, Worker, WorkerState, MAX_WORKERS, WORKER_CAPACITY, WORKER_PRICE},
+};
+use common::{counter, move_player, new_game, remove_trees};
+
+// a game without trees, with the player standing on the lumber camp
+fn new_game_at_camp() -> App {
+    let mut app = new_game();
+    remove_trees(&mut app);
+    let camp = app.world.resource::<MapLayout>().lumber_camps[0];
+    move_player(&mut app, camp);
+    app
+}
+
+fn hire(app: &mut App) {
+    app.world.resource_mut::<PlayerInput>().interact = true;
+    app.update();
+    // the worker is spawned at the end of the tick
+    app.update();
+}
+
+fn workers(app: &mut App) -> Vec<(WorkerState, u32)> {
+    app.world
+        .query::<&Worker>()
+        .iter(&app.world)
+        .map(|worker| (worker.state(), worker.wood()))
+        .collect()
+}
+
+fn spawn_tree(app: &mut App, tile: IVec2, health: i16) -> Entity {
+    app.world
+        .spawn()
+        .insert(Tree { health })
+        .insert(Transform::from_translation(tile_to_world(tile).extend(0.0)))
+        .id()
+}
+
+#[test]
+fn workers_are_hired_with_coins_at_the_camp() {
+    let mut app = new_game_at_camp();
+
+    hire(&mut app);
+    assert!(workers(&mut app).is_empty());
+
+    counter::<CoinResource>(&mut app).0 = WORKER_PRICE * (MAX_WORKERS as u32 + 1);
+    for _ in 0..MAX_WORKERS {
+        hire(&mut app);
+    }
+    assert_eq!(workers(&mut app).len(), MAX_WORKERS);
+    assert_eq!(counter::<CoinResource>(&mut app).0, WORKER_PRICE);
+
+    // the camp is full, the coins are kept
+    hire(&mut app);
+    assert_eq!(workers(&mut app).len(), MAX_WORKERS);
+    assert_eq!(counter::<CoinResource>(&mut app).0, WORKER_PRICE);
+}
+
+#[test]
+fn worker_chops_the_closest_tree_and_delivers_its_wood() {
+    let mut app = new_game_at_camp();
+    let camp = app.world.resource::<MapLayout>().lumber_camps[0];
+    let close_tree = spawn_tree(&mut app, camp + IVec2::new(3, 0), 200);
+    let far_tree = spawn_tree(&mut app, camp + IVec2::new(6, -4), 100);
+
+    counter::<CoinResource>(&mut app).0 = WORKER_PRICE;
+    hire(&mut app);
+    assert_eq!(workers(&mut app), vec![(WorkerState::Idle, 0)]);
+
+    let mut states = Vec::new();
+    for _ in 0..600 {
+        app.update();
+        let state = workers(&mut app)[0].0;
+        if states.last() != Some(&state) {
+            states.push(state);
+        }
+        if counter::<WoodResource>(&mut app).0 > 0 {
+            break;
+        }
+    }
+
+    // the worker only carries a few logs, the tree is still standing after its first trip
+    assert_eq!(
+        states,
+        vec![
+            WorkerState::Idle,
+            WorkerState::Seek,
+            WorkerState::Chop,
+            WorkerState::Idle,
+            WorkerState::Deliver,
+            WorkerState::Idle,
+        ]
+    );
+    assert_eq!(counter::<WoodResource>(&mut app).0, WORKER_CAPACITY);
+    assert_eq!(workers(&mut app)[0].1, 0);
+    assert_eq!(app.world.get::<Tree>(close_tree).unwrap().health, 100);
+    assert_eq!(app.world.get::<Tree>(far_tree).unwrap().health, 100);
+    assert_eq!(
+        app.world.resource::<GameStats>().wood_chopped,
+        WORKER_CAPACITY
+    );
+
+    // then it goes back to fell the tree
+    for _ in 0..1200 {
+        app.update();
+        if counter::<WoodResource>(&mut app).0 >= 2 * WORKER_CAPACITY {
+            break;
+        }
+    }
+    assert_eq!(counter::<WoodResource>(&mut app).0, 2 * WORKER_CAPACITY);
+    assert!(app.world.get::<Tree>(close_tree).is_none());
+    assert_eq!(app.world.get::<Tree>(far_tree).unwrap().health, 100);
+    assert_eq!(app.world.resource::<GameStats>().trees_felled, 1);
+}
+
+#[test]
+fn two_workers_fell_a_tree_once() {
+    let mut app = new_game_at_camp();
+    let camp = app.world.resource::<MapLayout>().lumber_camps[0];
+    // both workers hit it on the same tick, the first hit fells it
+    let tree = spawn_tree(&mut app, camp + IVec2::new(3, 0), 20);
+
+    let atlas = AtlasHandle(app.world.resource::<AtlasHandle>().0.clone());
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    for _ in 0..2 {
+        spawn_worker(&mut commands, &atlas, tile_to_world(camp).extend(0.0));
+    }
+    queue.apply(&mut app.world);
+
+    for _ in 0..600 {
+        app.update();
+        if app.world.get::<Tree>(tree).is_none() {
+            break;
+        }
+    }
+    assert!(app.world.get::<Tree>(tree).is_none());
+    assert_eq!(app.world.resource::<GameStats>().trees_felled, 1);
+    assert_eq!(app.world.resource::<GameStats>().wood_chopped, 1);
+    let mut wood: Vec<u32> = workers(&mut app).iter().map(|(_, wood)| *wood).collect();
+    wood.sort_unstable();
+    assert_eq!(wood, vec![0, 1]);
+}